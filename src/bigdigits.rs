@@ -0,0 +1,59 @@
+//  A minimalist terminal‐based focus timer and stopwatch with daily logging, built in Rust
+//  Copyright (C) 2025  Arda Yılmaz
+//
+//  This program is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  This program is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU General Public License for more details.
+//
+//  You should have received a copy of the GNU General Public License
+//  along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Large block-digit rendering for the clock display.
+
+/// Number of lines a rendered clock takes up.
+pub const HEIGHT: usize = 5;
+
+fn glyph(c: char) -> Option<[&'static str; HEIGHT]> {
+    let g = match c {
+        '0' => ["███", "█ █", "█ █", "█ █", "███"],
+        '1' => ["  █", "  █", "  █", "  █", "  █"],
+        '2' => ["███", "  █", "███", "█  ", "███"],
+        '3' => ["███", "  █", "███", "  █", "███"],
+        '4' => ["█ █", "█ █", "███", "  █", "  █"],
+        '5' => ["███", "█  ", "███", "  █", "███"],
+        '6' => ["███", "█  ", "███", "█ █", "███"],
+        '7' => ["███", "  █", "  █", "  █", "  █"],
+        '8' => ["███", "█ █", "███", "█ █", "███"],
+        '9' => ["███", "█ █", "███", "  █", "███"],
+        ':' => [" ", "█", " ", "█", " "],
+        '.' => [" ", " ", " ", " ", "█"],
+        _ => return None,
+    };
+    Some(g)
+}
+
+/// Width in columns of `text` once rendered, or `None` if it contains a
+/// character the font has no glyph for.
+pub fn width(text: &str) -> Option<usize> {
+    let mut total = 0;
+    for c in text.chars() {
+        total += glyph(c)?[0].chars().count() + 1;
+    }
+    Some(total.saturating_sub(1))
+}
+
+/// Renders a clock string such as `25:00.00` as `HEIGHT` lines of block
+/// digits. Returns `None` if `text` contains an unsupported character.
+pub fn render(text: &str) -> Option<String> {
+    let glyphs = text.chars().map(glyph).collect::<Option<Vec<_>>>()?;
+    let lines: Vec<String> = (0..HEIGHT)
+        .map(|row| glyphs.iter().map(|g| g[row]).collect::<Vec<_>>().join(" "))
+        .collect();
+    Some(lines.join("\n"))
+}
@@ -21,93 +21,691 @@ use crossterm::{
 };
 use ratatui::{
     backend::CrosstermBackend,
-    layout::{Alignment, Constraint, Direction, Layout},
-    style::{Color, Style},
-    widgets::{Block, Borders, Paragraph},
+    layout::{Alignment, Constraint, Direction, Layout, Margin, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{
+        BarChart, Block, Borders, Clear, Gauge, Paragraph, Row, Scrollbar, ScrollbarOrientation,
+        ScrollbarState, Sparkline, Table, TableState, Wrap,
+    },
     Terminal,
 };
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Mutex,
+    },
     fs,
     io::{self, stdout},
-    path::PathBuf,
-    time::{Duration, Instant},
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
-use chrono::{Local, NaiveDate};
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveDateTime, NaiveTime, Timelike, Weekday};
 use serde::{Deserialize, Serialize};
-use serde_json;
+use unicode_width::UnicodeWidthStr;
+
+mod bigdigits;
 
 const CONFIG_TIMER_MIN: u64 = 1;
 const CONFIG_TIMER_MAX: u64 = 999;
+const STATUS_MESSAGE_DURATION: Duration = Duration::from_secs(2);
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(15);
+const IDLE_POLL: Duration = Duration::from_millis(250);
+const STATUS_FILE_INTERVAL: Duration = Duration::from_secs(1);
+const SPINNER: [char; 4] = ['◐', '◓', '◑', '◒'];
+const SPINNER_FRAME: Duration = Duration::from_millis(250);
+// Shown with show_quotes when the config doesn't list its own.
+const DEFAULT_QUOTES: [&str; 8] = [
+    "One thing at a time.",
+    "Start where you are.",
+    "Small steps still move you forward.",
+    "Done is better than perfect.",
+    "Just the next five minutes.",
+    "Rest is part of the work.",
+    "The hard part is starting.",
+    "Attention is where the work happens.",
+];
+// Below this the layout has no room left to work with.
+const MIN_WIDTH: u16 = 30;
+const MIN_HEIGHT: u16 = 10;
+// The most days --export-svg --last draws, about ten years.
+const SVG_MAX_DAYS: u64 = 3660;
+
+// Cleared by --no-save; every write to history, sessions and checkpoints
+// checks it first.
+static PERSIST: AtomicBool = AtomicBool::new(true);
+// Bumped whenever a session is written, so cached counts know to re-read.
+static SESSIONS_WRITTEN: AtomicU64 = AtomicU64::new(0);
+// Set by --force: take the lock over from whichever instance holds it.
+static FORCE_LOCK: AtomicBool = AtomicBool::new(false);
+
+fn persisting() -> bool {
+    PERSIST.load(Ordering::Relaxed)
+}
 
 #[derive(Serialize, Deserialize, Debug)]
+#[serde(default)]
 struct Config {
     default_timer_duration: u64,
+    remember_last_timer: bool,
+    timer_step_minutes: u64,
+    timer_coarse_step_minutes: u64,
     default_start_page: usize,
+    remember_last_page: bool,
+    clipboard_enabled: bool,
+    resume_max_age_minutes: u64,
+    daily_goal: u64,
+    weekly_goal: u64,
+    daily_pomodoro_goal: u64,
+    week_start: String,
+    celebrate_record: bool,
+    rich_summary: bool,
+    always_show_today: bool,
+    show_trend: bool,
+    big_clock: bool,
+    strict_focus: bool,
+    show_clock: bool,
+    clock_format: String,
+    confirm_quit_while_running: bool,
+    quit_behavior: String,
+    log_partial_timer: bool,
+    min_session_seconds: u64,
+    rounding: String,
+    auto_break_stopwatch: bool,
+    autosave_minutes: u64,
+    idle_pause_minutes: u64,
+    auto_quit_idle_minutes: u64,
+    stretch_reminder_minutes: u64,
+    refresh_ms: u64,
+    session_notes: bool,
+    status_file: bool,
+    rearm_target: String,
+    gauge_direction: String,
+    show_session_elapsed: bool,
+    show_finish_time: bool,
+    animations: bool,
+    final_warning_seconds: u64,
+    final_warning_blink: bool,
+    date_display_format: String,
+    human_readable_durations: bool,
+    pomodoro_enabled: bool,
+    work_duration: u64,
+    short_break: u64,
+    long_break: u64,
+    cycles_before_long_break: u64,
+    notifications_enabled: bool,
+    sound_enabled: bool,
+    sound_command: Option<String>,
+    tags: Vec<String>,
+    show_quotes: bool,
+    quotes: Vec<String>,
+    presets: Vec<Preset>,
+    quiet_hours: QuietHours,
+    // The same window as top-level keys, folded into `quiet_hours` on load.
+    quiet_hours_start: String,
+    quiet_hours_end: String,
+    theme: Theme,
+    keybindings: HashMap<String, Vec<String>>,
+}
+
+/// A named timer duration, cycled through with [p] on the timer page.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Preset {
+    name: String,
+    minutes: u64,
+}
+
+/// A daily window, as "HH:MM", in which notifications and sounds stay off.
+/// It may wrap past midnight, e.g. 22:00 to 07:00.
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
+struct QuietHours {
+    start: String,
+    end: String,
+}
+
+impl QuietHours {
+    fn window(&self) -> Option<(NaiveTime, NaiveTime)> {
+        let parse = |s: &str| NaiveTime::parse_from_str(s, "%H:%M").ok();
+        Some((parse(&self.start)?, parse(&self.end)?))
+    }
+
+    fn is_quiet(&self, now: NaiveTime) -> bool {
+        self.window().is_some_and(|(start, end)| in_quiet_hours(now, start, end))
+    }
+}
+
+/// Whether `now` falls in the window from `start` (inclusive) to `end`
+/// (exclusive), which wraps past midnight when `end` comes before `start`.
+/// An empty window, with `start == end`, is never quiet.
+fn in_quiet_hours(now: NaiveTime, start: NaiveTime, end: NaiveTime) -> bool {
+    if start <= end {
+        start <= now && now < end
+    } else {
+        now >= start || now < end
+    }
+}
+
+/// When someone last used fokus, given that `event` arrived at `at`. Only a
+/// key press is someone at the keyboard; resizes from a tiling window manager
+/// or tmux, and focus or mouse events, leave the idle deadlines alone.
+fn last_activity(last_input: Instant, event: &Event, at: Instant) -> Instant {
+    match event {
+        Event::Key(_) => at,
+        _ => last_input,
+    }
+}
+
+/// Whether `minutes` have passed since `last_input`. 0 never does, since it
+/// turns the idle pause and auto-quit off.
+fn idle_for(last_input: Instant, now: Instant, minutes: u64) -> bool {
+    minutes > 0 && now.saturating_duration_since(last_input) >= Duration::from_secs(minutes * 60)
+}
+
+/// Index of the quote to show after `prev` out of `len`, never the same one
+/// twice in a row when there is a choice. `seed` only has to differ between
+/// calls.
+fn next_quote(prev: usize, len: usize, seed: u64) -> usize {
+    match len {
+        0 | 1 => 0,
+        _ => (prev + 1 + (seed % (len as u64 - 1)) as usize) % len,
+    }
+}
+
+fn quote_seed() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos() as u64)
+}
+
+/// Seconds from `now` until the next time the clock reads `target`: later
+/// today, or tomorrow if that time has already passed.
+fn seconds_until(now: NaiveDateTime, target: NaiveTime) -> u64 {
+    let mut at = now.date().and_time(target);
+    if at <= now {
+        at = at + chrono::Days::new(1);
+    }
+    (at - now).num_seconds().max(0) as u64
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(default)]
+struct Theme {
+    header_color: String,
+    title_color: String,
+    accent_color: String,
+    border_color: String,
+    footer_color: String,
+    alert_color: String,
+    goal_color: String,
+    heatmap_colors: Vec<String>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            header_color: "cyan".to_string(),
+            title_color: "green".to_string(),
+            accent_color: "yellow".to_string(),
+            border_color: "gray".to_string(),
+            footer_color: "gray".to_string(),
+            alert_color: "red".to_string(),
+            goal_color: "green".to_string(),
+            heatmap_colors: ["darkgray", "#0e4429", "#006d32", "#26a641", "#39d353"]
+                .iter()
+                .map(|c| c.to_string())
+                .collect(),
+        }
+    }
+}
+
+struct Palette {
+    header: Color,
+    title: Color,
+    accent: Color,
+    border: Color,
+    footer: Color,
+    alert: Color,
+    goal: Color,
+    // Empty days first, then one color per quartile.
+    heatmap: [Color; 5],
+}
+
+impl Theme {
+    fn palette(&self) -> Palette {
+        let def = Theme::default();
+        let pick = |s: &str, fallback: &str| parse_color(s).or_else(|| parse_color(fallback)).unwrap_or_default();
+        Palette {
+            header: pick(&self.header_color, &def.header_color),
+            title: pick(&self.title_color, &def.title_color),
+            accent: pick(&self.accent_color, &def.accent_color),
+            border: pick(&self.border_color, &def.border_color),
+            footer: pick(&self.footer_color, &def.footer_color),
+            alert: pick(&self.alert_color, &def.alert_color),
+            goal: pick(&self.goal_color, &def.goal_color),
+            heatmap: std::array::from_fn(|i| {
+                pick(self.heatmap_colors.get(i).map_or("", String::as_str), &def.heatmap_colors[i])
+            }),
+        }
+    }
+}
+
+fn parse_color(s: &str) -> Option<Color> {
+    let s = s.trim();
+    if let Some(hex) = s.strip_prefix('#') {
+        if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        return Some(Color::Rgb(channel(0)?, channel(2)?, channel(4)?));
+    }
+    s.parse::<Color>().ok()
 }
 
 impl Default for Config {
     fn default() -> Self {
         Config {
             default_timer_duration: 25,
+            remember_last_timer: false,
+            timer_step_minutes: 1,
+            timer_coarse_step_minutes: 15,
             default_start_page: 0,
+            remember_last_page: false,
+            clipboard_enabled: true,
+            resume_max_age_minutes: 60,
+            daily_goal: 0,
+            weekly_goal: 0,
+            daily_pomodoro_goal: 0,
+            week_start: "monday".to_string(),
+            celebrate_record: true,
+            rich_summary: false,
+            always_show_today: false,
+            show_trend: false,
+            big_clock: false,
+            strict_focus: false,
+            show_clock: false,
+            clock_format: "24h".to_string(),
+            confirm_quit_while_running: false,
+            quit_behavior: "log".to_string(),
+            log_partial_timer: true,
+            min_session_seconds: 0,
+            rounding: "exact".to_string(),
+            auto_break_stopwatch: false,
+            autosave_minutes: 5,
+            idle_pause_minutes: 0,
+            auto_quit_idle_minutes: 0,
+            stretch_reminder_minutes: 0,
+            refresh_ms: 10,
+            session_notes: false,
+            status_file: false,
+            rearm_target: "default".to_string(),
+            gauge_direction: "fill".to_string(),
+            show_session_elapsed: false,
+            show_finish_time: false,
+            animations: false,
+            final_warning_seconds: 10,
+            final_warning_blink: false,
+            date_display_format: "%Y-%m-%d".to_string(),
+            human_readable_durations: false,
+            pomodoro_enabled: false,
+            work_duration: 25,
+            short_break: 5,
+            long_break: 15,
+            cycles_before_long_break: 4,
+            notifications_enabled: true,
+            sound_enabled: false,
+            sound_command: None,
+            tags: Vec::new(),
+            show_quotes: false,
+            quotes: Vec::new(),
+            presets: Vec::new(),
+            quiet_hours: QuietHours::default(),
+            quiet_hours_start: String::new(),
+            quiet_hours_end: String::new(),
+            theme: Theme::default(),
+            keybindings: Action::ALL
+                .iter()
+                .map(|a| (a.name().to_string(), a.default_keys().iter().map(|k| k.to_string()).collect()))
+                .collect(),
         }
     }
 }
 
 impl Config {
+    fn first_weekday(&self) -> Weekday {
+        if self.week_start == "sunday" { Weekday::Sun } else { Weekday::Mon }
+    }
+
+    /// Moves `quiet_hours_start` and `quiet_hours_end` into the
+    /// `[quiet_hours]` table, unless the table already sets a window.
+    fn fold_quiet_hours(&mut self) {
+        let start = std::mem::take(&mut self.quiet_hours_start);
+        let end = std::mem::take(&mut self.quiet_hours_end);
+        if self.quiet_hours.start.is_empty() && self.quiet_hours.end.is_empty() {
+            self.quiet_hours = QuietHours { start, end };
+        }
+    }
+
     fn config_path() -> Option<PathBuf> {
-        dirs_next::config_dir().map(|d| d.join("fokus").join("config.toml"))
+        env_path("FOKUS_CONFIG").or_else(|| fokus_dir().map(|d| d.join("config.toml")))
+    }
+
+    fn default_toml() -> String {
+        Config::default().to_toml()
+    }
+
+    fn to_toml(&self) -> String {
+        let sound_command = match &self.sound_command {
+            Some(cmd) => format!("sound_command = {:?}", cmd),
+            None => "# sound_command = \"paplay /usr/share/sounds/freedesktop/stereo/complete.oga\"".to_string(),
+        };
+        let mut s = format!(
+            "# fokus Configuration File\n\n\
+             # Default timer duration (in minutes)\n\
+             # Must be between {} and {}\n\
+             default_timer_duration = {}\n\n\
+             # Start the timer at the duration it had when fokus last quit\n\
+             remember_last_timer = {}\n\n\
+             # How much [j]/[k] and [J]/[K] change the timer by (in minutes)\n\
+             # Must be between 1 and 60\n\
+             timer_step_minutes = {}\n\
+             timer_coarse_step_minutes = {}\n\n\
+             # Default start page\n\
+             # 0 = Stopwatch, 1 = Timer, 2 = History\n\
+             default_start_page = {}\n\n\
+             # Open on the page that was showing when fokus last quit, instead of default_start_page\n\
+             remember_last_page = {}\n\n\
+             # Copy today's summary to the clipboard with [c]\n\
+             clipboard_enabled = {}\n\n\
+             # Ignore timer checkpoints older than this when launched with --resume (in minutes)\n\
+             resume_max_age_minutes = {}\n\n\
+             # Daily focus goal (in minutes), shown as progress below the clock\n\
+             # 0 = Disabled\n\
+             daily_goal = {}\n\n\
+             # Weekly focus goal (in minutes) for the current week, shown in the top left corner\n\
+             # 0 = Disabled\n\
+             weekly_goal = {}\n\n\
+             # Focus timers to finish each day, counted below the timer (stopping one early doesn't count)\n\
+             # 0 = Disabled\n\
+             daily_pomodoro_goal = {}\n\n\
+             # First day of the week, for the weekly goal, \"This week\" and the heatmap\n\
+             # \"monday\" or \"sunday\"\n\
+             week_start = \"{}\"\n\n\
+             # Notify once when today beats your best day so far (needs notifications_enabled)\n\
+             celebrate_record = {}\n\n\
+             # Show minutes, sessions, goal progress and streak together below the clock\n\
+             rich_summary = {}\n\n\
+             # Keep today's total visible while a session is running\n\
+             always_show_today = {}\n\n\
+             # Show a one-line chart of the last 14 days below the clock\n\
+             show_trend = {}\n\n\
+             # Draw the stopwatch and timer in large block digits when the terminal is big enough\n\
+             big_clock = {}\n\n\
+             # While the stopwatch or timer runs, show only the clock and ignore every key\n\
+             # except [space] (pause or stop, which leaves this screen) and [q]\n\
+             strict_focus = {}\n\n\
+             # Show the time of day in the top right corner\n\
+             # clock_format: \"24h\" = 14:05, \"12h\" = 2:05 PM\n\
+             show_clock = {}\n\
+             clock_format = \"{}\"\n\n\
+             # Ask before quitting while the stopwatch or timer is running\n\
+             confirm_quit_while_running = {}\n\n\
+             # What [q] does with a session that is still running\n\
+             # \"log\" = save it, \"discard\" = drop it, \"confirm\" = ask which\n\
+             # Break intervals are never logged, and time already autosaved stays logged\n\
+             quit_behavior = \"{}\"\n\n\
+             # Log the elapsed part of a focus interval that is stopped or skipped early\n\
+             log_partial_timer = {}\n\n\
+             # Don't log a stopwatch or stopped timer session shorter than this (in seconds)\n\
+             # 0 = Log everything\n\
+             min_session_seconds = {}\n\n\
+             # How a stopped stopwatch or timer session is rounded to whole minutes when logged\n\
+             # \"exact\" = keep the seconds, \"floor\", \"round\" or \"ceil\" = down, nearest or up\n\
+             rounding = \"{}\"\n\n\
+             # When a focus timer finishes (outside Pomodoro mode), count the break on the stopwatch page\n\
+             # Break time is never logged; [space] ends the break and goes back to the timer\n\
+             auto_break_stopwatch = {}\n\n\
+             # Save a running stopwatch to the history this often, in case fokus is killed (in minutes)\n\
+             # 0 = Only when it is reset or fokus quits\n\
+             autosave_minutes = {}\n\n\
+             # Pause the stopwatch after this long without a key press, in case you walked away (in minutes)\n\
+             # 0 = Disabled\n\
+             idle_pause_minutes = {}\n\n\
+             # Quit after this long without a key press while neither the stopwatch nor the timer runs (in minutes)\n\
+             # Time on a paused stopwatch is handled as on [q]. 0 = Disabled\n\
+             auto_quit_idle_minutes = {}\n\n\
+             # Remind you to take a stretch after every this many minutes the stopwatch runs without a pause\n\
+             # 0 = Disabled\n\
+             stretch_reminder_minutes = {}\n\n\
+             # How often the screen refreshes while the stopwatch or timer runs (in milliseconds)\n\
+             # fokus checks only a few times a second while idle\n\
+             refresh_ms = {}\n\n\
+             # Ask for a one-line note when a focus timer finishes (kept in sessions.json)\n\
+             session_notes = {}\n\n\
+             # Keep status.json next to the history up to date while fokus runs, for status bars\n\
+             status_file = {}\n\n\
+             # What [d] resets the timer to\n\
+             # \"default\" = default_timer_duration, \"last\" = the last started duration\n\
+             rearm_target = \"{}\"\n\n\
+             # Direction of the progress bar shown while the timer runs\n\
+             # \"fill\" = fills up as time passes, \"drain\" = empties as time runs out\n\
+             gauge_direction = \"{}\"\n\n\
+             # Also show how long the running timer has been going, below the countdown\n\
+             show_session_elapsed = {}\n\n\
+             # Show the time of day the running timer will finish at, above the countdown\n\
+             show_finish_time = {}\n\n\
+             # Spin a small indicator in the title while the stopwatch or timer runs\n\
+             animations = {}\n\n\
+             # Color the running timer in its last seconds: accent_color, then alert_color\n\
+             # for the final half. 0 = Disabled\n\
+             final_warning_seconds = {}\n\
+             # Also blink the timer during those seconds (if the terminal supports it)\n\
+             final_warning_blink = {}\n\n\
+             # How dates are shown on the History page, in strftime syntax (e.g. \"%d.%m.%Y\")\n\
+             # The history file and the [f] and [/] prompts always use YYYY-MM-DD\n\
+             date_display_format = {:?}\n\n\
+             # Show an hour or more as e.g. \"2h 15m\" instead of \"135 minutes\", below the clock and in the history\n\
+             human_readable_durations = {}\n\n\
+             # Pomodoro mode: alternate work intervals with short and long breaks\n\
+             # Durations are in minutes and must be between {} and {}\n\
+             pomodoro_enabled = {}\n\
+             work_duration = {}\n\
+             short_break = {}\n\
+             long_break = {}\n\
+             cycles_before_long_break = {}\n\n\
+             # Show a desktop notification when the timer finishes\n\
+             notifications_enabled = {}\n\n\
+             # Play a sound when the timer finishes\n\
+             # Rings the terminal bell unless sound_command is set\n\
+             sound_enabled = {}\n\
+             {}\n\n\
+             # Project tags to attribute focus time to, cycled with [t]\n\
+             # e.g. [\"writing\", \"coding\"]\n\
+             tags = {:?}\n\n\
+             # Show a short quote below the stopwatch or timer while it isn't running\n\
+             # quotes replaces the built-in ones, e.g. [\"Keep going.\"]\n\
+             show_quotes = {}\n\
+             quotes = {:?}\n\n\
+             # Named timer durations to cycle through with [p] on the timer page\n\
+             # Minutes must be between {} and {}\n",
+            CONFIG_TIMER_MIN, CONFIG_TIMER_MAX, self.default_timer_duration, self.remember_last_timer,
+            self.timer_step_minutes, self.timer_coarse_step_minutes,
+            self.default_start_page, self.remember_last_page,
+            self.clipboard_enabled, self.resume_max_age_minutes, self.daily_goal, self.weekly_goal, self.daily_pomodoro_goal, self.week_start, self.celebrate_record,
+            self.rich_summary,
+            self.always_show_today, self.show_trend, self.big_clock, self.strict_focus, self.show_clock, self.clock_format,
+            self.confirm_quit_while_running, self.quit_behavior, self.log_partial_timer, self.min_session_seconds, self.rounding,
+            self.auto_break_stopwatch,
+            self.autosave_minutes, self.idle_pause_minutes, self.auto_quit_idle_minutes,
+            self.stretch_reminder_minutes,
+            self.refresh_ms, self.session_notes, self.status_file, self.rearm_target, self.gauge_direction, self.show_session_elapsed, self.show_finish_time, self.animations,
+            self.final_warning_seconds, self.final_warning_blink, self.date_display_format, self.human_readable_durations,
+            CONFIG_TIMER_MIN, CONFIG_TIMER_MAX,
+            self.pomodoro_enabled, self.work_duration, self.short_break, self.long_break,
+            self.cycles_before_long_break, self.notifications_enabled, self.sound_enabled,
+            sound_command, self.tags, self.show_quotes, self.quotes, CONFIG_TIMER_MIN, CONFIG_TIMER_MAX
+        );
+        if self.presets.is_empty() {
+            s.push_str("# [[presets]]\n# name = \"deep work\"\n# minutes = 50\n");
+        }
+        let presets: Vec<String> = self.presets.iter()
+            .map(|p| format!("[[presets]]\nname = {:?}\nminutes = {}\n", p.name, p.minutes))
+            .collect();
+        s.push_str(&presets.join("\n"));
+
+        s.push_str(&format!(
+            "\n[quiet_hours]\n\
+             # No notifications or sounds between these times (HH:MM), e.g. \"22:00\" to \"07:00\"\n\
+             # Time is still logged and shown as usual. Leave empty to disable\n\
+             # (quiet_hours_start and quiet_hours_end at the top of the file work too)\n\
+             start = {:?}\n\
+             end = {:?}\n",
+            self.quiet_hours.start, self.quiet_hours.end
+        ));
+
+        s.push_str(&format!(
+            "\n[theme]\n\
+             # Named colors (e.g. \"cyan\", \"lightblue\") or hex values (e.g. \"#ff8800\")\n\
+             header_color = {:?}\n\
+             title_color = {:?}\n\
+             accent_color = {:?}\n\
+             border_color = {:?}\n\
+             footer_color = {:?}\n\
+             alert_color = {:?}\n\
+             goal_color = {:?}\n\
+             # Heatmap shades: days with nothing logged, then each quartile from least to most\n\
+             heatmap_colors = {:?}\n",
+            self.theme.header_color, self.theme.title_color, self.theme.accent_color,
+            self.theme.border_color, self.theme.footer_color, self.theme.alert_color,
+            self.theme.goal_color, self.theme.heatmap_colors
+        ));
+
+        s.push_str(
+            "\n[keybindings]\n\
+             # Each action takes a list of keys: a single character, two characters typed\n\
+             # one after the other (e.g. \"gg\", History page only), or one of\n\
+             # Space, Enter, Esc, Backspace, Tab, Left, Right, Up, Down, PageUp, PageDown, Home, End, Delete, F1-F12\n",
+        );
+        for action in Action::ALL {
+            let keys = self.keybindings.get(action.name()).cloned().unwrap_or_default();
+            s.push_str(&format!("{} = {:?}\n", action.name(), keys));
+        }
+        s
     }
 
-    fn load_or_create() -> io::Result<Config> {
+    /// Loads the config, writing out the defaults or any repairs. Failing to
+    /// write doesn't stop fokus: the settings are used as loaded and the error
+    /// is returned alongside them, to be shown in the footer.
+    fn load_or_create() -> (Config, Option<io::Error>) {
+        let mut write_error = None;
+        let mut save = |path: &Path, s: String| {
+            let written = match path.parent() {
+                Some(parent) => fs::create_dir_all(parent).and_then(|_| fs::write(path, s)),
+                None => fs::write(path, s),
+            };
+            if let Err(e) = written {
+                write_error.get_or_insert(e);
+            }
+        };
+
         let cfg = match Self::config_path() {
             Some(path) => {
-                if let Some(parent) = path.parent() {
-                    fs::create_dir_all(parent)?; 
-                }
-
                 if path.exists() {
-                    let s = fs::read_to_string(&path)?;
+                    let s = match fs::read_to_string(&path) {
+                        Ok(s) => s,
+                        Err(e) => return (Config::default(), Some(e)),
+                    };
                     match toml::from_str::<Config>(&s) {
                         Ok(mut cfg) => {
 
                             if cfg.default_timer_duration < CONFIG_TIMER_MIN || cfg.default_timer_duration > CONFIG_TIMER_MAX {
-                                let def = Config::default();
-                                let toml_s = format!(
-                                    "# fokus Configuration File\n\n\
-                                 # Default timer duration (in minutes)\n\
-                                 # Must be between {} and {}\n\
-                                 default_timer_duration = {}\n\n\
-                                 # Default start page\n
-                                 # 0 = Stopwatch, 1 = Timer, 2 = History\n\
-                                 default_start_page = {}",
-                                 CONFIG_TIMER_MIN, CONFIG_TIMER_MAX, def.default_timer_duration, def.default_start_page
-                                );
-                                fs::write(&path, toml_s)?;
-                                def
+                                save(&path, Self::default_toml());
+                                Config::default()
                             } else {
                                 cfg.default_start_page = match cfg.default_start_page {
-    0..=2 => cfg.default_start_page,
-    _ => 0,
-};
+                                    0..=2 => cfg.default_start_page,
+                                    _ => 0,
+                                };
+                                if !matches!(cfg.rearm_target.as_str(), "default" | "last") {
+                                    cfg.rearm_target = Config::default().rearm_target;
+                                }
+                                if !matches!(cfg.gauge_direction.as_str(), "fill" | "drain") {
+                                    cfg.gauge_direction = Config::default().gauge_direction;
+                                }
+                                // Formatting fails (and to_string would panic) on unknown
+                                // specifiers or ones a date has no value for, like %H.
+                                let formats = {
+                                    use std::fmt::Write;
+                                    let sample = NaiveDate::from_ymd_opt(2025, 1, 31).unwrap_or_default();
+                                    write!(String::new(), "{}", sample.format(&cfg.date_display_format)).is_ok()
+                                };
+                                if cfg.date_display_format.is_empty() || !formats {
+                                    cfg.date_display_format = Config::default().date_display_format;
+                                }
+                                if !matches!(cfg.quit_behavior.as_str(), "log" | "discard" | "confirm") {
+                                    cfg.quit_behavior = Config::default().quit_behavior;
+                                }
+                                if !matches!(cfg.rounding.as_str(), "exact" | "floor" | "round" | "ceil") {
+                                    cfg.rounding = Config::default().rounding;
+                                }
+                                if !matches!(cfg.week_start.as_str(), "monday" | "sunday") {
+                                    cfg.week_start = Config::default().week_start;
+                                }
+                                if !matches!(cfg.clock_format.as_str(), "24h" | "12h") {
+                                    cfg.clock_format = Config::default().clock_format;
+                                }
+                                let def = Config::default();
+                                let in_bounds = |m: u64| (CONFIG_TIMER_MIN..=CONFIG_TIMER_MAX).contains(&m);
+                                if !in_bounds(cfg.work_duration) {
+                                    cfg.work_duration = def.work_duration;
+                                }
+                                if !in_bounds(cfg.short_break) {
+                                    cfg.short_break = def.short_break;
+                                }
+                                if !in_bounds(cfg.long_break) {
+                                    cfg.long_break = def.long_break;
+                                }
+                                cfg.presets.retain(|p| in_bounds(p.minutes));
+                                if cfg.cycles_before_long_break == 0 {
+                                    cfg.cycles_before_long_break = def.cycles_before_long_break;
+                                }
+                                if cfg.refresh_ms == 0 {
+                                    cfg.refresh_ms = def.refresh_ms;
+                                }
+                                if !(1..=60).contains(&cfg.timer_step_minutes) {
+                                    cfg.timer_step_minutes = def.timer_step_minutes;
+                                }
+                                if !(1..=60).contains(&cfg.timer_coarse_step_minutes) {
+                                    cfg.timer_coarse_step_minutes = def.timer_coarse_step_minutes;
+                                }
+                                cfg.fold_quiet_hours();
+                                if cfg.quiet_hours.window().is_none() {
+                                    cfg.quiet_hours = def.quiet_hours;
+                                }
+
+                                let mut repaired = false;
+                                for action in Action::ALL {
+                                    let valid = cfg.keybindings.get(action.name()).is_some_and(|keys| {
+                                        !keys.is_empty() && keys.iter().all(|k| parse_key(k).is_some() || k.chars().count() == 2)
+                                    });
+                                    if !valid {
+                                        cfg.keybindings.insert(action.name().to_string(), def.keybindings[action.name()].clone());
+                                        repaired = true;
+                                    }
+                                }
+                                if repaired {
+                                    save(&path, cfg.to_toml());
+                                }
                                 cfg
                             }
                         }
                         Err(_) => {
-                            let def = Config::default();
-                    let toml_s = format!("# fokus Configuration File\n\n# Default timer duration (in minutes)\n# Must be between {} and {}\ndefault_timer_duration = {}\n\n# Default start page\n# 0 = Stopwatch, 1 = Timer, 2 = History\ndefault_start_page = {}", CONFIG_TIMER_MIN, CONFIG_TIMER_MAX, def.default_timer_duration, def.default_start_page);
-
-                            fs::write(&path, toml_s)?;
-                            def
+                            save(&path, Self::default_toml());
+                            Config::default()
                         }
                     }
                 } else {
-
-                    let def = Config::default();
-                    let toml_s = format!("# fokus Configuration File\n\n# Default timer duration (in minutes)\n# Must be between {} and {}\ndefault_timer_duration = {}\n\n# Default start page\n# 0 = Stopwatch, 1 = Timer, 2 = History\ndefault_start_page = {}", CONFIG_TIMER_MIN, CONFIG_TIMER_MAX, def.default_timer_duration, def.default_start_page);
-                    fs::write(&path, toml_s)?;
-                    def
+                    save(&path, Self::default_toml());
+                    Config::default()
                 }
             }
             None => {
@@ -115,459 +713,4773 @@ impl Config {
                 Config::default()
             }
         };
-        Ok(cfg)
+        (cfg, write_error)
     }
 }
 
-fn history_path() -> Option<PathBuf> {
-    dirs_next::config_dir().map(|d| d.join("fokus").join("history.json"))
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Action {
+    Quit,
+    NextPage,
+    PrevPage,
+    Increase,
+    Decrease,
+    IncreaseMore,
+    DecreaseMore,
+    Toggle,
+    Reset,
+    Rearm,
+    Filter,
+    ClearFilter,
+    Copy,
+    Input,
+    Tag,
+    View,
+    Undo,
+    Search,
+    Preset,
+    Delete,
+    Pause,
+    Help,
+    Move,
+    Export,
+    FinishGoal,
+    PageUp,
+    PageDown,
+    Top,
+    Bottom,
 }
 
-fn load_or_create_history() -> io::Result<HashMap<String, u64>> {
-    if let Some(path) = history_path() {
-        if let Some(parent) = path.parent() {
-            fs::create_dir_all(parent)?; 
+impl Action {
+    const ALL: [Action; 29] = [
+        Action::Quit,
+        Action::NextPage,
+        Action::PrevPage,
+        Action::Increase,
+        Action::Decrease,
+        Action::IncreaseMore,
+        Action::DecreaseMore,
+        Action::Toggle,
+        Action::Reset,
+        Action::Rearm,
+        Action::Filter,
+        Action::ClearFilter,
+        Action::Copy,
+        Action::Input,
+        Action::Tag,
+        Action::View,
+        Action::Undo,
+        Action::Search,
+        Action::Preset,
+        Action::Delete,
+        Action::Pause,
+        Action::Help,
+        Action::Move,
+        Action::Export,
+        Action::FinishGoal,
+        Action::PageUp,
+        Action::PageDown,
+        Action::Top,
+        Action::Bottom,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::NextPage => "next_page",
+            Action::PrevPage => "prev_page",
+            Action::Increase => "increase",
+            Action::Decrease => "decrease",
+            Action::IncreaseMore => "increase_more",
+            Action::DecreaseMore => "decrease_more",
+            Action::Toggle => "toggle",
+            Action::Reset => "reset",
+            Action::Rearm => "rearm",
+            Action::Filter => "filter",
+            Action::ClearFilter => "clear_filter",
+            Action::Copy => "copy",
+            Action::Input => "input",
+            Action::Tag => "tag",
+            Action::View => "view",
+            Action::Undo => "undo",
+            Action::Search => "search",
+            Action::Preset => "preset",
+            Action::Delete => "delete",
+            Action::Pause => "pause",
+            Action::Help => "help",
+            Action::Move => "move",
+            Action::Export => "export",
+            Action::FinishGoal => "finish_goal",
+            Action::PageUp => "page_up",
+            Action::PageDown => "page_down",
+            Action::Top => "top",
+            Action::Bottom => "bottom",
         }
+    }
 
-        if path.exists() {
-            let s = fs::read_to_string(&path)?;
-            match serde_json::from_str::<HashMap<String, u64>>(&s) {
-                Ok(map) => Ok(map),
-                Err(_) => {
+    /// What the action does, as listed in the help overlay.
+    fn description(self) -> &'static str {
+        match self {
+            Action::Quit => "Quit",
+            Action::NextPage => "Next page",
+            Action::PrevPage => "Previous page",
+            Action::Increase => "Timer +1 step / scroll up",
+            Action::Decrease => "Timer -1 step / scroll down",
+            Action::IncreaseMore => "Timer + coarse step",
+            Action::DecreaseMore => "Timer - coarse step",
+            Action::Toggle => "Start/stop the clock",
+            Action::Reset => "Reset stopwatch/Pomodoro",
+            Action::Rearm => "Reset timer duration",
+            Action::Filter => "Filter history by date",
+            Action::ClearFilter => "Clear filter and search",
+            Action::Copy => "Copy today's summary",
+            Action::Input => "Type timer duration",
+            Action::Tag => "Cycle tag",
+            Action::View => "Cycle history view",
+            Action::Undo => "Undo last session",
+            Action::Search => "Search history",
+            Action::Preset => "Cycle timer preset",
+            Action::Delete => "Delete highlighted day",
+            Action::Pause => "Pause/resume timer",
+            Action::Help => "Show/hide this help",
+            Action::Move => "Move/merge day into a date",
+            Action::Export => "Export history as CSV",
+            Action::FinishGoal => "Set the timer to finish the daily goal",
+            Action::PageUp => "History page up",
+            Action::PageDown => "History page down",
+            Action::Top => "History top",
+            Action::Bottom => "History bottom",
+        }
+    }
+
+    fn default_keys(self) -> &'static [&'static str] {
+        match self {
+            Action::Quit => &["q"],
+            Action::NextPage => &["l", "Right"],
+            Action::PrevPage => &["h", "Left"],
+            Action::Increase => &["k", "Up"],
+            Action::Decrease => &["j", "Down"],
+            Action::IncreaseMore => &["K"],
+            Action::DecreaseMore => &["J"],
+            Action::Toggle => &["Space"],
+            Action::Reset => &["r"],
+            Action::Rearm => &["d"],
+            Action::Filter => &["f"],
+            Action::ClearFilter => &["Esc"],
+            Action::Copy => &["c"],
+            Action::Input => &["i"],
+            Action::Tag => &["t"],
+            Action::View => &["v"],
+            Action::Undo => &["u"],
+            Action::Search => &["/"],
+            Action::Preset => &["p"],
+            Action::Delete => &["x"],
+            Action::Pause => &["s"],
+            Action::Help => &["?"],
+            Action::Move => &["m"],
+            Action::Export => &["e"],
+            Action::FinishGoal => &["a"],
+            Action::PageUp => &["PageUp"],
+            Action::PageDown => &["PageDown"],
+            Action::Top => &["Home", "gg"],
+            Action::Bottom => &["End", "G"],
+        }
+    }
+}
+
+fn parse_key(s: &str) -> Option<KeyCode> {
+    let mut chars = s.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(KeyCode::Char(c));
+    }
+
+    let code = match s.to_ascii_lowercase().as_str() {
+        "space" => KeyCode::Char(' '),
+        "enter" => KeyCode::Enter,
+        "esc" => KeyCode::Esc,
+        "backspace" => KeyCode::Backspace,
+        "tab" => KeyCode::Tab,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "delete" => KeyCode::Delete,
+        f => match f.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+            Some(n @ 1..=12) => KeyCode::F(n),
+            _ => return None,
+        },
+    };
+    Some(code)
+}
 
-                    let date_str = Local::now().format("%Y%m%d_%H%M%S").to_string();
-                    let backup_path = path.with_file_name(format!(
-                            "history_{}.json.bak",
-                            date_str
-                    ));
+fn key_name(code: KeyCode) -> String {
+    match code {
+        KeyCode::Char(' ') => "space".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::Enter => "enter".to_string(),
+        KeyCode::Esc => "esc".to_string(),
+        KeyCode::Backspace => "backspace".to_string(),
+        KeyCode::Tab => "tab".to_string(),
+        KeyCode::Left => "←".to_string(),
+        KeyCode::Right => "→".to_string(),
+        KeyCode::Up => "↑".to_string(),
+        KeyCode::Down => "↓".to_string(),
+        KeyCode::PageUp => "pgup".to_string(),
+        KeyCode::PageDown => "pgdn".to_string(),
+        KeyCode::Home => "home".to_string(),
+        KeyCode::End => "end".to_string(),
+        KeyCode::Delete => "del".to_string(),
+        KeyCode::F(n) => format!("F{}", n),
+        _ => "?".to_string(),
+    }
+}
 
-                    fs::copy(&path, &backup_path)?;
+struct Keymap {
+    bindings: Vec<(KeyCode, Action)>,
+    /// Two keys pressed one after the other, written as e.g. `gg`.
+    chords: Vec<((KeyCode, KeyCode), Action)>,
+}
 
-                    let empty: HashMap<String, u64> = HashMap::new();
-                    let s2 = serde_json::to_string_pretty(&empty)
-                        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
-                    fs::write(&path, s2)?;
-                    Ok(empty)
+impl Keymap {
+    fn from_config(keybindings: &HashMap<String, Vec<String>>) -> Keymap {
+        let mut bindings = Vec::new();
+        let mut chords = Vec::new();
+        for action in Action::ALL {
+            for key in keybindings.get(action.name()).into_iter().flatten() {
+                let mut chars = key.chars();
+                if let Some(code) = parse_key(key) {
+                    bindings.push((code, action));
+                } else if let (Some(a), Some(b), None) = (chars.next(), chars.next(), chars.next()) {
+                    chords.push(((KeyCode::Char(a), KeyCode::Char(b)), action));
                 }
             }
-        } else {
+        }
+        Keymap { bindings, chords }
+    }
+
+    fn action(&self, code: KeyCode) -> Option<Action> {
+        self.bindings.iter().find(|(c, _)| *c == code).map(|(_, a)| *a)
+    }
+
+    fn chord(&self, first: KeyCode, second: KeyCode) -> Option<Action> {
+        self.chords.iter().find(|(c, _)| *c == (first, second)).map(|(_, a)| *a)
+    }
+
+    fn starts_chord(&self, code: KeyCode) -> bool {
+        self.chords.iter().any(|((first, _), _)| *first == code)
+    }
+
+    /// Every key bound to `action`, for the help overlay.
+    fn labels(&self, action: Action) -> String {
+        let keys = self.bindings.iter()
+            .filter(|(_, a)| *a == action)
+            .map(|(c, _)| key_name(*c));
+        let chords = self.chords.iter()
+            .filter(|(_, a)| *a == action)
+            .map(|((first, second), _)| format!("{}{}", key_name(*first), key_name(*second)));
+        keys.chain(chords).collect::<Vec<_>>().join("/")
+    }
+
+    fn label(&self, action: Action) -> String {
+        self.bindings
+            .iter()
+            .find(|(_, a)| *a == action)
+            .map(|(c, _)| key_name(*c))
+            .unwrap_or_default()
+    }
+}
+
+fn env_path(var: &str) -> Option<PathBuf> {
+    std::env::var_os(var).filter(|v| !v.is_empty()).map(PathBuf::from)
+}
+
+/// Where fokus keeps its files: `$FOKUS_CONFIG_DIR` if set, otherwise
+/// `fokus` under the platform config directory.
+fn fokus_dir() -> Option<PathBuf> {
+    env_path("FOKUS_CONFIG_DIR").or_else(|| dirs_next::config_dir().map(|d| d.join("fokus")))
+}
+
+fn history_path() -> Option<PathBuf> {
+    env_path("FOKUS_HISTORY").or_else(|| fokus_dir().map(|d| d.join("history.json")))
+}
+
+const HISTORY_VERSION: u32 = 1;
+
+/// Seconds per tag, keyed by date and then by tag.
+type TagHistory = HashMap<String, HashMap<String, u64>>;
+
+#[derive(Serialize, Deserialize)]
+struct HistoryFile {
+    version: u32,
+    data: HashMap<String, u64>,
+    // Absent in files written before tags existed.
+    #[serde(default)]
+    tags: TagHistory,
+}
+
+// The history as this process last read or wrote it. Saving applies the
+// difference between it and the new contents to whatever is on disk by then,
+// so changes made in the meantime (by --add, or another instance started with
+// --no-lock) aren't overwritten.
+static SAVED_HISTORY: Mutex<Option<(HashMap<String, u64>, TagHistory)>> = Mutex::new(None);
+
+fn remember_saved(map: &HashMap<String, u64>, tags: &TagHistory) {
+    if let Ok(mut saved) = SAVED_HISTORY.lock() {
+        *saved = Some((map.clone(), tags.clone()));
+    }
+}
 
-            let empty: HashMap<String, u64> = HashMap::new();
-            let s = serde_json::to_string_pretty(&empty)
-                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
-            fs::write(&path, s)?;
-            Ok(empty)
+/// Applies the change from `base` to `mine` to `disk`, key by key. Keys whose
+/// value didn't change are left as they are on disk; ones that reach zero are
+/// removed.
+fn apply_delta(disk: &mut HashMap<String, u64>, base: &HashMap<String, u64>, mine: &HashMap<String, u64>) {
+    let keys: HashSet<&String> = base.keys().chain(mine.keys()).collect();
+    for key in keys {
+        let old = base.get(key).copied().unwrap_or(0);
+        let new = mine.get(key).copied().unwrap_or(0);
+        if old == new {
+            continue;
+        }
+        let current = disk.get(key).copied().unwrap_or(0);
+        let merged = if new > old { current.saturating_add(new - old) } else { current.saturating_sub(old - new) };
+        if merged == 0 {
+            disk.remove(key);
+        } else {
+            disk.insert(key.clone(), merged);
         }
-    } else {
-        Ok(HashMap::new())
     }
 }
 
-fn _save_history(map: &HashMap<String, u64>) -> io::Result<()> {
-    if let Some(path) = history_path() {
-        if let Some(parent) = path.parent() {
-            fs::create_dir_all(parent)?;
+fn merge_history(
+    disk: (&mut HashMap<String, u64>, &mut TagHistory),
+    base: (&HashMap<String, u64>, &TagHistory),
+    mine: (&HashMap<String, u64>, &TagHistory),
+) {
+    apply_delta(disk.0, base.0, mine.0);
+    let empty = HashMap::new();
+    let days: HashSet<&String> = base.1.keys().chain(mine.1.keys()).collect();
+    for day in days {
+        let day_tags = disk.1.entry(day.clone()).or_default();
+        apply_delta(day_tags, base.1.get(day).unwrap_or(&empty), mine.1.get(day).unwrap_or(&empty));
+        if day_tags.is_empty() {
+            disk.1.remove(day);
         }
-        let s = serde_json::to_string_pretty(map)
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
-        fs::write(path, s)?;
-        Ok(())
-    } else {
-        Err(io::Error::new(io::ErrorKind::NotFound, "Config directory not found"))
     }
 }
 
-fn lock_path() -> Option<PathBuf> {
-    dirs_next::config_dir().map(|d| d.join("fokus").join("fokus.lock"))
+/// Refuses files from a newer fokus, which would otherwise look unreadable
+/// and be reset.
+fn check_history_version(s: &str) -> io::Result<()> {
+    let version = serde_json::from_str::<serde_json::Value>(s)
+        .ok()
+        .and_then(|v| v.get("version").and_then(serde_json::Value::as_u64));
+    match version {
+        Some(v) if v > HISTORY_VERSION as u64 => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "History file format version {} is newer than this fokus supports ({}); please upgrade fokus",
+                    v, HISTORY_VERSION,
+                ),
+        )),
+        _ => Ok(()),
+    }
 }
 
-fn acquire_lock() -> io::Result<(fs::File, PathBuf)> {
-    if let Some(path) = lock_path() {
+fn parse_history(s: &str) -> Option<(HashMap<String, u64>, TagHistory, bool)> {
+    if let Ok(file) = serde_json::from_str::<HistoryFile>(s) {
+        return Some((file.data, file.tags, false));
+    }
+
+    // Files without a version marker stored whole minutes instead of seconds.
+    serde_json::from_str::<HashMap<String, u64>>(s).ok().map(|map| {
+        let seconds = map.into_iter().map(|(k, v)| (k, v.saturating_mul(60))).collect();
+        (seconds, TagHistory::new(), true)
+    })
+}
+
+fn load_or_create_history() -> io::Result<(HashMap<String, u64>, TagHistory)> {
+    if let Some(path) = history_path() {
         if let Some(parent) = path.parent() {
-            fs::create_dir_all(parent)?;
+            fs::create_dir_all(parent)?; 
         }
 
         if path.exists() {
-            if let Ok(s) = fs::read_to_string(&path) {
-                if let Ok(pid) = s.trim().parse::<u32>() {
-                    let proc_path = PathBuf::from("/proc").join(pid.to_string());
-                    if proc_path.exists() {
-                        return Err(io::Error::new(
-                                io::ErrorKind::AlreadyExists,
-                                "Please kill the other instance before starting a new one.",
-                        ));
-                    } else {
-                        let _ = fs::remove_file(&path);
+            let s = fs::read_to_string(&path)?;
+            check_history_version(&s)?;
+            match parse_history(&s) {
+                Some((mut map, mut tags, upgraded)) => {
+                    if upgraded {
+                        backup_history(&path)?;
+                        _save_history(&mut map, &mut tags)?;
                     }
-                } else {
-                    let _ = fs::remove_file(&path);
+                    remember_saved(&map, &tags);
+                    Ok((map, tags))
+                }
+                None => {
+
+                    backup_history(&path)?;
+
+                    let mut empty: HashMap<String, u64> = HashMap::new();
+                    let mut tags = TagHistory::new();
+                    _save_history(&mut empty, &mut tags)?;
+                    Ok((empty, tags))
                 }
-            } else {
-                let _ = fs::remove_file(&path);
             }
-        }
+        } else {
 
-        let mut f = fs::OpenOptions::new()
-            .write(true)
-            .create_new(true)
-            .open(&path)?;
-        let pid = std::process::id();
-        use std::io::Write;
-        let _ = write!(f, "{}", pid);
-        Ok((f, path))
+            let mut empty: HashMap<String, u64> = HashMap::new();
+            let mut tags = TagHistory::new();
+            _save_history(&mut empty, &mut tags)?;
+            Ok((empty, tags))
+        }
     } else {
-        Err(io::Error::new(io::ErrorKind::NotFound, "Config directory could not be found."))
+        Ok((HashMap::new(), TagHistory::new()))
     }
 }
 
-fn main() -> io::Result<()> {
+fn read_history() -> io::Result<(HashMap<String, u64>, TagHistory)> {
+    let (map, tags) = match history_path() {
+        Some(path) => read_disk_history(&path)?,
+        None => (HashMap::new(), TagHistory::new()),
+    };
+    remember_saved(&map, &tags);
+    Ok((map, tags))
+}
 
-    let config = Config::load_or_create()?;
+/// The history currently on disk, for merging into. Unlike read_history this
+/// leaves SAVED_HISTORY alone.
+fn read_disk_history(path: &Path) -> io::Result<(HashMap<String, u64>, TagHistory)> {
+    if !path.exists() {
+        return Ok((HashMap::new(), TagHistory::new()));
+    }
+    let s = fs::read_to_string(path)?;
+    check_history_version(&s)?;
+    parse_history(&s)
+        .map(|(map, tags, _)| (map, tags))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "History file could not be parsed"))
+}
 
-    let (lock_file, lock_path_buf) = match acquire_lock() {
-        Ok(t) => t,
-        Err(e) => {
-            eprintln!("fokus: {}", e);
-            std::process::exit(1);
-        }
-    };
+/// Where the history is kept. The TUI only reads and changes it through this,
+/// so the JSON file could be swapped for another backend.
+trait HistoryStore {
+    /// Seconds logged on `day`, a YYYY-MM-DD key (or whatever a hand-edited
+    /// file holds).
+    fn total_for(&self, day: &str) -> u64;
+    /// Every day logged and its seconds, in no particular order, including
+    /// keys that aren't dates.
+    fn entries(&self) -> Vec<(String, u64)>;
+    /// The seconds logged under each tag on `day`.
+    fn tags_on(&self, day: &str) -> Vec<(String, u64)>;
+    /// Adds `seconds` to `day` (and to `tag` on that day), returning the
+    /// day's new total.
+    fn add(&mut self, day: &str, tag: Option<&str>, seconds: u64) -> u64;
+    /// Takes `seconds` back off `day` and `tag`, dropping entries that reach
+    /// zero. Returns the seconds actually removed.
+    fn subtract(&mut self, day: &str, tag: Option<&str>, seconds: u64) -> u64;
+    /// Removes `day` and its tags, returning the seconds it had.
+    fn delete(&mut self, day: &str) -> u64;
+    /// Moves everything logged under `from` to the date `to`, adding to what
+    /// `to` already has. Returns whether `to` already existed, i.e. the days
+    /// were merged rather than renamed.
+    fn move_day(&mut self, from: &str, to: &str) -> bool;
+    /// Writes out whatever changed since the last flush.
+    fn flush(&mut self) -> io::Result<()>;
 
-    let mut timer_total = Duration::from_secs(config.default_timer_duration * 60);
+    /// The days keyed by a valid date, oldest first.
+    fn iter_sorted(&self) -> Vec<(String, u64)> {
+        let mut dated: Vec<(NaiveDate, String, u64)> = self.entries().into_iter()
+            .filter_map(|(k, secs)| Some((NaiveDate::parse_from_str(&k, "%Y-%m-%d").ok()?, k, secs)))
+            .collect();
+        dated.sort();
+        dated.into_iter().map(|(_d, k, secs)| (k, secs)).collect()
+    }
 
-    let mut history = load_or_create_history()?;
+    /// Like `iter_sorted`, with the keys parsed.
+    fn dated(&self) -> Vec<(NaiveDate, u64)> {
+        self.iter_sorted().into_iter()
+            .filter_map(|(k, secs)| Some((NaiveDate::parse_from_str(&k, "%Y-%m-%d").ok()?, secs)))
+            .collect()
+    }
+}
 
-    enable_raw_mode()?;
-    let mut stdout = stdout();
-    execute!(stdout, EnterAlternateScreen)?; 
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+/// history.json, held in memory and saved on every flush.
+struct JsonHistory {
+    days: HashMap<String, u64>,
+    tags: TagHistory,
+}
 
-    let header_pages = ["< Page 1 of 3 >", "< Page 2 of 3 >", "< Page 3 of 3 >"];
-    let mut header_page_index = config.default_start_page;
+impl JsonHistory {
+    fn read() -> io::Result<JsonHistory> {
+        let (days, tags) = read_history()?;
+        Ok(JsonHistory { days, tags })
+    }
+}
 
-    let mut stopwatch_start = Instant::now();
-    let mut stopwatch_running = false;
-    let mut stopwatch_display = "00:00.00".to_string();
+impl HistoryStore for JsonHistory {
+    fn total_for(&self, day: &str) -> u64 {
+        self.days.get(day).copied().unwrap_or(0)
+    }
 
-    let extra = Duration::from_secs(60); 
-    let timer_max = Duration::from_secs(60*999);
-    let timer_min = Duration::from_secs(60*1);
+    fn entries(&self) -> Vec<(String, u64)> {
+        self.days.iter().map(|(k, secs)| (k.clone(), *secs)).collect()
+    }
 
-    let mut timer_start = Instant::now();
-    let mut timer_running = false;
-    let mut timer_display = format_duration(timer_total);
-    let mut timer_logged = false; 
-    let mut timer_done = false; 
+    fn tags_on(&self, day: &str) -> Vec<(String, u64)> {
+        self.tags.get(day).into_iter().flatten().map(|(tag, secs)| (tag.clone(), *secs)).collect()
+    }
 
-    let mut history_offset = 0; 
+    fn add(&mut self, day: &str, tag: Option<&str>, seconds: u64) -> u64 {
+        if let Some(tag) = tag {
+            *self.tags.entry(day.to_string()).or_default().entry(tag.to_string()).or_insert(0) += seconds;
+        }
+        let total = self.days.entry(day.to_string()).or_insert(0);
+        *total = total.saturating_add(seconds);
+        *total
+    }
 
-    terminal.clear()?;
-    terminal.hide_cursor()?;
+    fn subtract(&mut self, day: &str, tag: Option<&str>, seconds: u64) -> u64 {
+        let removed = match self.days.get_mut(day) {
+            Some(secs) => {
+                let removed = seconds.min(*secs);
+                *secs -= removed;
+                removed
+            }
+            None => 0,
+        };
+        if self.days.get(day) == Some(&0) {
+            self.days.remove(day);
+        }
 
-    loop {
+        if let Some(tag) = tag && let Some(day_tags) = self.tags.get_mut(day) {
+            if let Some(secs) = day_tags.get_mut(tag) {
+                *secs = secs.saturating_sub(seconds);
+                if *secs == 0 {
+                    day_tags.remove(tag);
+                }
+            }
+            if day_tags.is_empty() {
+                self.tags.remove(day);
+            }
+        }
+        removed
+    }
 
-        let area = terminal.size()?;
-        let middle_height = area.height / 2; 
-        let visible_height = (middle_height as usize).saturating_sub(2);
+    fn delete(&mut self, day: &str) -> u64 {
+        self.tags.remove(day);
+        self.days.remove(day).unwrap_or(0)
+    }
 
-        terminal.draw(|f| {
+    fn move_day(&mut self, from: &str, to: &str) -> bool {
+        if from == to {
+            return true;
+        }
+        let merged = self.days.contains_key(to);
+        if let Some(secs) = self.days.remove(from) {
+            self.add(to, None, secs);
+        }
+        if let Some(from_tags) = self.tags.remove(from) {
+            let to_tags = self.tags.entry(to.to_string()).or_default();
+            for (tag, secs) in from_tags {
+                *to_tags.entry(tag).or_insert(0) += secs;
+            }
+        }
+        merged
+    }
 
-            let chunks = Layout::default()
-                .direction(Direction::Vertical)
-                .constraints([
-                    Constraint::Length(2),      
-                    Constraint::Min(0),         
-                    Constraint::Length(2),      
-                ])
-                .split(f.area());
+    fn flush(&mut self) -> io::Result<()> {
+        _save_history(&mut self.days, &mut self.tags)
+    }
+}
 
-            let header = Paragraph::new(format!("\n{}", header_pages[header_page_index]))
-                .alignment(Alignment::Center)
-                .style(Style::default().fg(Color::Cyan));
-            f.render_widget(header, chunks[0]);
+fn log_seconds(store: &mut dyn HistoryStore, tag: Option<&str>, seconds: u64) {
+    log_seconds_until(store, Local::now().naive_local(), tag, seconds);
+}
 
-            let middle_chunks = if header_page_index == 2 {
-                Layout::default()
-                    .direction(Direction::Vertical)
-                    .constraints([
-                        Constraint::Percentage(25), 
-                        Constraint::Min(visible_height as u16), 
-                        Constraint::Length(1),      
-                        Constraint::Percentage(25), 
-                    ])
-                    .split(chunks[1])
-            } else {
+/// Logs `seconds` that ran up to `end`, split between the days they ran on.
+/// They are taken to have run back to back, so time paused across midnight
+/// goes to the wrong day; the main loop logs what is on the clock as the
+/// date changes to keep that from happening to a paused stopwatch.
+fn log_seconds_until(store: &mut dyn HistoryStore, end: NaiveDateTime, tag: Option<&str>, seconds: u64) {
+    if seconds == 0 {
+        return;
+    }
+    let end = end.with_nanosecond(0).unwrap_or(end);
+    let start = end - chrono::Duration::seconds(seconds.min(i64::MAX as u64) as i64);
+    for (day, secs) in split_at_midnight(start, end) {
+        store.add(&day.format("%Y-%m-%d").to_string(), tag, secs);
+    }
+    if let Err(e) = store.flush() {
+        eprintln!("Failed to save history: {}", e);
+    }
+}
 
-                let content_height: usize = 3;
-                let area_h = chunks[1].height as usize;
+/// The whole seconds from `start` to `end` that fall on each calendar day,
+/// oldest first. Days with nothing in them are left out.
+fn split_at_midnight(start: NaiveDateTime, end: NaiveDateTime) -> Vec<(NaiveDate, u64)> {
+    let total = (end - start).num_seconds().max(0) as u64;
+    let mut parts = Vec::new();
+    let mut counted = 0;
+    let mut from = start;
+    while counted < total {
+        let midnight = from.date().succ_opt().map(|d| d.and_time(NaiveTime::MIN));
+        let secs = match midnight {
+            Some(midnight) if midnight < end => ((midnight - from).num_seconds().max(0) as u64).min(total - counted),
+            // The last day takes the rest, so the parts always add up.
+            _ => total - counted,
+        };
+        if secs > 0 {
+            parts.push((from.date(), secs));
+        }
+        counted += secs;
+        match midnight {
+            Some(midnight) => from = midnight,
+            None => break,
+        }
+    }
+    parts
+}
 
-                let remaining = area_h.saturating_sub(content_height + 1);
-                let top = (remaining / 2) as u16;
-                let bottom = (remaining - (remaining / 2)) as u16;
+fn backup_history(path: &Path) -> io::Result<PathBuf> {
+    let date_str = Local::now().format("%Y%m%d_%H%M%S").to_string();
+    let backup_path = path.with_file_name(format!(
+            "history_{}.json.bak",
+            date_str
+    ));
 
-                Layout::default()
-                    .direction(Direction::Vertical)
-                    .constraints([
-                        Constraint::Length(top),                    
-                        Constraint::Length(content_height as u16),  
-                        Constraint::Length(1),                      
-                        Constraint::Length(bottom),                 
-                    ])
-                    .split(chunks[1])
-            };
+    fs::copy(path, &backup_path)?;
+    Ok(backup_path)
+}
 
-            let middle_inner = Layout::default()
-                .direction(Direction::Horizontal)
-                .constraints([
-                    Constraint::Percentage(30), 
-                    Constraint::Percentage(40), 
-                    Constraint::Percentage(30), 
-                ])
-                .split(middle_chunks[1]);
+fn history_backups() -> io::Result<Vec<PathBuf>> {
+    let dir = match history_path().as_deref().and_then(Path::parent) {
+        Some(d) => d.to_path_buf(),
+        None => return Err(io::Error::new(io::ErrorKind::NotFound, "Config directory not found")),
+    };
 
-            let middle_text = match header_page_index {
-                0 => {
+    let mut backups: Vec<PathBuf> = Vec::new();
+    if dir.exists() {
+        for entry in fs::read_dir(dir)? {
+            let p = entry?.path();
+            let name = p.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            if name.starts_with("history_") && name.ends_with(".json.bak") {
+                backups.push(p);
+            }
+        }
+    }
+    backups.sort();
+    Ok(backups)
+}
 
-                    if stopwatch_running {
-                        let elapsed = stopwatch_start.elapsed();
-                        stopwatch_display = format!(
-                            "{:02}:{:02}.{:02}",
-                            elapsed.as_secs() / 60,
-                            elapsed.as_secs() % 60,
-                            elapsed.subsec_millis() / 10
-                        );
-                    }
-                    stopwatch_display.clone()
-                }
-                1 => {
-                    if timer_running {
-                        let elapsed = timer_start.elapsed();
-                        let remaining = if elapsed >= timer_total {
-                            Duration::ZERO
-                        } else {
-                            timer_total - elapsed
-                        };
-                        timer_display = format_duration(remaining);
-                        if remaining == Duration::ZERO && !timer_logged {
-                            timer_running = false;
-                            timer_done = true;
-                            let minutes = timer_total.as_secs() / 60;
-                            if minutes > 0 {
-                                let today = Local::now().format("%Y-%m-%d").to_string();
-                                *history.entry(today).or_insert(0) += minutes;
-                                if let Err(e) = _save_history(&history) {
-                                    eprintln!("Failed to save history: {}", e);
-                                }
-                            }
-                            timer_logged = true;
-                        }
-                    }
-                    timer_display.clone()
-                }
-                2 => {
+fn list_backups() -> io::Result<()> {
+    let backups = history_backups()?;
+    if backups.is_empty() {
+        println!("No history backups found.");
+        return Ok(());
+    }
 
-                    let mut table = String::new();
+    for p in backups {
+        let name = p.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        let taken = name
+            .strip_prefix("history_")
+            .and_then(|n| n.strip_suffix(".json.bak"))
+            .and_then(|n| NaiveDateTime::parse_from_str(n, "%Y%m%d_%H%M%S").ok())
+            .map(|d| d.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        let size = fs::metadata(&p).map(|m| m.len()).unwrap_or(0);
+        println!("{}  {}  {} bytes", name, taken, size);
+    }
+    Ok(())
+}
 
-                    let mut parsed: Vec<(chrono::NaiveDate, String)> = Vec::new();
-                    let mut unparsable: Vec<String> = Vec::new();
-                    for k in history.keys() {
-                        match NaiveDate::parse_from_str(k, "%Y-%m-%d") {
-                            Ok(d) => parsed.push((d, k.clone())),
-                            Err(_) => unparsable.push(k.clone()),
-                        }
-                    }
-                    parsed.sort_by(|a, b| b.0.cmp(&a.0));
+fn restore_backup(name: &str) -> io::Result<()> {
+    let backup = history_backups()?
+        .into_iter()
+        .find(|p| p.file_name().and_then(|n| n.to_str()) == Some(name))
+        .ok_or_else(|| io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("No backup named {} (see --list-backups)", name),
+        ))?;
+
+    let s = fs::read_to_string(&backup)?;
+    check_history_version(&s)?;
+    if parse_history(&s).is_none() {
+        return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} is not a valid history file", name),
+        ));
+    }
 
-                    parsed.extend(unparsable.into_iter().map(|s| (NaiveDate::from_ymd_opt(1970,1,1).unwrap(), s)));
+    print!("Restore {} over the current history? [y/N] ", name);
+    io::Write::flush(&mut io::stdout())?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    if !matches!(answer.trim(), "y" | "Y" | "yes") {
+        println!("Restore cancelled.");
+        return Ok(());
+    }
 
-                    let widget_height = middle_inner[1].height as usize;
-                    let header_rows = 2; 
-                    let available_rows = widget_height.saturating_sub(header_rows);
+    with_lock(|| match history_path() {
+        Some(path) => {
+            if path.exists() {
+                let fresh = backup_history(&path)?;
+                println!("Current history backed up to {}", fresh.display());
+            }
+            write_atomic(&path, &s)
+        }
+        None => Err(io::Error::new(io::ErrorKind::NotFound, "Config directory not found")),
+    })?;
 
-                    let total_rows = parsed.len();
+    println!("Restored history from {}", name);
+    Ok(())
+}
 
-                    if total_rows <= available_rows {
-                        history_offset = 0;
-                    } else if history_offset > total_rows.saturating_sub(available_rows) {
-                        history_offset = total_rows.saturating_sub(available_rows);
-                    }
+const UNDO_DEPTH: usize = 10;
 
-                    let end = (history_offset + available_rows).min(total_rows);
-                    let visible = &parsed[history_offset..end];
+/// A finished session, kept so it can be taken back from the History page.
+struct LoggedSession {
+    day: String,
+    tag: Option<String>,
+    seconds: u64,
+    // When its line in sessions.json ended, if it has one, and whether that
+    // line is a pomodoro.
+    ended_at: Option<String>,
+    completed: bool,
+}
 
-                    table.push_str(&format!("{:<11} | {:>6}\n", "Date", "Minutes"));
-                    table.push_str(&"-".repeat(21));
-                    table.push('\n');
+impl LoggedSession {
+    fn wrote(&self, record: &SessionRecord) -> bool {
+        self.ended_at.as_ref() == Some(&record.ended_at) && self.tag == record.tag && self.completed == record.completed
+    }
+}
 
-                    for (_d, key) in visible {
-                        let minutes = history.get(key.as_str()).copied().unwrap_or(0);
-                        table.push_str(&format!("{:<11} | {:>6}\n", key, minutes));
-                    }
+/// Remembers that `seconds` were logged for `record`, which is about to be
+/// written to sessions.json.
+fn push_undo(stack: &mut Vec<LoggedSession>, record: &SessionRecord, seconds: u64) {
+    if seconds == 0 {
+        return;
+    }
+    if stack.len() == UNDO_DEPTH {
+        stack.remove(0);
+    }
+    stack.push(LoggedSession {
+        day: record.ended_on().unwrap_or_else(|| Local::now().date_naive()).format("%Y-%m-%d").to_string(),
+        tag: record.tag.clone(),
+        seconds,
+        ended_at: Some(record.ended_at.clone()),
+        completed: record.completed,
+    });
+}
 
-                    table
-                }
-                _ => "".to_string(),
-            };
+/// Subtracts a session from its day (and tag). Returns the seconds actually
+/// removed.
+fn undo_session(store: &mut dyn HistoryStore, session: &LoggedSession) -> u64 {
+    let removed = store.subtract(&session.day, session.tag.as_deref(), session.seconds);
+    if let Err(e) = store.flush() {
+        eprintln!("Failed to save history: {}", e);
+    }
+    removed
+}
 
-            let middle_style = if header_page_index == 1 && !timer_running && timer_display == "00:00.00" {
-                Style::default().fg(Color::Red)
-            } else {
-                Style::default().fg(Color::default())
-            };
+fn add_minutes(minutes: u64, date: NaiveDate) -> io::Result<()> {
+    let day = date.format("%Y-%m-%d").to_string();
+    // No lock needed: a running instance merges this in when it next saves.
+    let mut store = JsonHistory::read()?;
+    let total = add_minutes_to(&mut store, &day, minutes)?;
+    store.flush()?;
+    println!("Added {} minutes to {}, {} minutes in total", minutes, day, total / 60);
+    Ok(())
+}
 
-            let middle = Paragraph::new(middle_text)
-                .alignment(Alignment::Center)
-                .block(
-                    Block::default()
-                    .borders(Borders::ALL) 
-                    .border_style(Style::default().fg(Color::Gray)) 
-                    .title(match header_page_index {
-                        0 => " Stopwatch ",
-                        1 => " Timer ",
-                        2 => " History ",
-                        _ => "",
-                    })
-                    .title_style(Style::default().fg(Color::Green)) 
-                    .title_alignment(Alignment::Left),
-                )
-                .style(middle_style);
-            f.render_widget(middle, middle_inner[1]);
+/// Adds `minutes` to `day`, returning the day's new total in seconds.
+fn add_minutes_to(store: &mut dyn HistoryStore, day: &str, minutes: u64) -> io::Result<u64> {
+    let seconds = minutes.checked_mul(60).ok_or_else(|| io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} minutes is more than can be logged", minutes),
+    ))?;
+    Ok(store.add(day, None, seconds))
+}
 
-            let today = Local::now().format("%Y-%m-%d").to_string();
-            let minutes_today = history.get(&today).cloned().unwrap_or(0);
-            let focused_minutes_text = Paragraph::new(format!("{} minutes focused today", minutes_today)) 
-                .alignment(Alignment::Center)
-                .style(Style::default().fg(Color::Yellow));
-            if header_page_index != 2 && !( (header_page_index == 0 && stopwatch_running) || (header_page_index == 1 && timer_running) ) {        
-                f.render_widget(focused_minutes_text, middle_chunks[2]);
+/// Adds every dated day in `days` (and its tags) to `store`, returning how
+/// many days were imported, the seconds added and the keys skipped for not
+/// being dates.
+fn merge_days(store: &mut dyn HistoryStore, days: &HashMap<String, u64>, tags: &TagHistory) -> (u64, u64, Vec<String>) {
+    let mut imported = 0;
+    let mut added = 0;
+    let mut skipped = Vec::new();
+    for (day, &secs) in days {
+        if NaiveDate::parse_from_str(day, "%Y-%m-%d").is_err() {
+            skipped.push(day.clone());
+            continue;
+        }
+        let mut tagged = 0;
+        for (tag, &tag_secs) in tags.get(day).into_iter().flatten() {
+            let tag_secs = tag_secs.min(secs - tagged);
+            store.add(day, Some(tag), tag_secs);
+            tagged += tag_secs;
+        }
+        store.add(day, None, secs - tagged);
+        imported += 1;
+        added += secs;
+    }
+    skipped.sort();
+    (imported, added, skipped)
+}
+
+fn import_history(path: &Path) -> io::Result<()> {
+    let s = fs::read_to_string(path)?;
+    check_history_version(&s)?;
+    let (days, tags, _) = parse_history(&s).ok_or_else(|| io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} is not a fokus history file", path.display()),
+    ))?;
+
+    // Additive like --add, so no lock: a running instance merges this in.
+    let mut store = JsonHistory::read()?;
+    let (imported, added, skipped) = merge_days(&mut store, &days, &tags);
+    store.flush()?;
+    for key in &skipped {
+        eprintln!("fokus: skipping unparsable date {:?}", key);
+    }
+    println!("Imported {} days, {} minutes added", imported, added / 60);
+    Ok(())
+}
+
+/// Removes `day` from the history and saves, returning the seconds it had.
+fn clear_day(store: &mut dyn HistoryStore, day: &str) -> io::Result<u64> {
+    let removed = store.delete(day);
+    store.flush()?;
+    Ok(removed)
+}
+
+fn reset_today() -> io::Result<()> {
+    let today = Local::now().format("%Y-%m-%d").to_string();
+    let removed = with_lock(|| clear_day(&mut JsonHistory::read()?, &today))?;
+    println!("Cleared {} minutes logged on {}", removed / 60, today);
+    Ok(())
+}
+
+fn clear_history(confirmed: bool) -> io::Result<()> {
+    if !confirmed {
+        print!("This deletes every day logged so far. Type \"yes\" to clear the history: ");
+        io::Write::flush(&mut io::stdout())?;
+        let mut answer = String::new();
+        io::stdin().read_line(&mut answer)?;
+        if answer.trim() != "yes" {
+            println!("Clear cancelled.");
+            return Ok(());
+        }
+    }
+
+    with_lock(|| match history_path() {
+        Some(path) => {
+            if path.exists() {
+                let backup = backup_history(&path)?;
+                println!("History backed up to {}", backup.display());
             }
+            let file = HistoryFile {
+                version: HISTORY_VERSION,
+                data: HashMap::new(),
+                tags: TagHistory::default(),
+            };
+            let s = serde_json::to_string_pretty(&file).map_err(io::Error::other)?;
+            write_atomic(&path, &s)
+        }
+        None => Err(io::Error::new(io::ErrorKind::NotFound, "Config directory not found")),
+    })?;
 
-            let footer = Paragraph::new("[space] Start/Reset [q] Quit [h]/[l] Change Page [j]/[k] Adjust/Scroll")
-                .alignment(Alignment::Center)
-                .style(Style::default().fg(Color::Gray));
-            f.render_widget(footer, chunks[2]);
-        })?;
+    println!("History cleared");
+    Ok(())
+}
 
-        if event::poll(std::time::Duration::from_millis(10))? {
-            if let Event::Key(key) = event::read()? {
-                match key.code {
-                    KeyCode::Right | KeyCode::Char('l') => {
-                        if !timer_running && !stopwatch_running {
-                            header_page_index = (header_page_index + 1) % header_pages.len();
-                        }
-                    }
-                    KeyCode::Left | KeyCode::Char('h') => {
-                        if !timer_running && !stopwatch_running {
-                            header_page_index = (header_page_index + header_pages.len() - 1) % header_pages.len();
-                        }    
-                    }
+fn write_atomic(path: &Path, contents: &str) -> io::Result<()> {
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".tmp");
+    let tmp = path.with_file_name(tmp_name);
 
-                    KeyCode::Up | KeyCode::Char('k') => {
-                        match header_page_index {
-                            1 => { 
-                                if !timer_running {
-                                    timer_total = (timer_total + extra).min(timer_max);
-                                    timer_display = format_duration(timer_total);
-                                }
-                            },
-                            2 => { 
-                                if history_offset > 0 {
-                                    history_offset -= 1;
-                                }
-                            },
-                            _ => {}
-                        }
-                    },
-                    KeyCode::Down | KeyCode::Char('j') => {
-                        match header_page_index {
-                            1 => { 
-                                if !timer_running {
-                                    timer_total = (timer_total.saturating_sub(extra)).max(timer_min);
-                                    timer_display = format_duration(timer_total);
-                                }
+    {
+        use std::io::Write;
+        let mut f = fs::File::create(&tmp)?;
+        f.write_all(contents.as_bytes())?;
+        f.sync_all()?;
+    }
+    fs::rename(&tmp, path)
+}
+
+/// Writes the history, merging in whatever changed on disk since it was last
+/// read or written here. `map` and `tags` are updated to the merged result.
+fn _save_history(map: &mut HashMap<String, u64>, tags: &mut TagHistory) -> io::Result<()> {
+    if !persisting() {
+        return Ok(());
+    }
+    if let Some(path) = history_path() {
+        let mut saved = SAVED_HISTORY.lock().map_err(|_| io::Error::other("History state poisoned"))?;
+        save_history_to(&path, map, tags, &mut saved)
+    } else {
+        Err(io::Error::new(io::ErrorKind::NotFound, "Config directory not found"))
+    }
+}
 
-                            },
+/// The read-merge-write behind `_save_history`. `saved` is the history as it
+/// was last read from or written to `path`, if ever, and is updated to what
+/// gets written.
+fn save_history_to(
+    path: &Path,
+    map: &mut HashMap<String, u64>,
+    tags: &mut TagHistory,
+    saved: &mut Option<(HashMap<String, u64>, TagHistory)>,
+) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if let Some((base_map, base_tags)) = saved.as_ref() {
+        let (mut data, mut merged_tags) = read_disk_history(path)?;
+        merge_history((&mut data, &mut merged_tags), (base_map, base_tags), (map, tags));
+        *map = data;
+        *tags = merged_tags;
+    }
+    let file = HistoryFile {
+        version: HISTORY_VERSION,
+        data: map.clone(),
+        tags: tags.clone(),
+    };
+    let s = serde_json::to_string_pretty(&file)
+        .map_err(io::Error::other)?;
+    write_atomic(path, &s)?;
+    *saved = Some((file.data, file.tags));
+    Ok(())
+}
 
-                            2 => { 
-                                history_offset = history_offset.saturating_add(1);
-                            },
-                            _ => {}
-                        }
-                    },
-                    KeyCode::Char(' ') => match header_page_index {
-                        0 => {
-
-                            if stopwatch_running {
-                                stopwatch_running = false;
-
-                                let elapsed = stopwatch_start.elapsed();
-                                let minutes = (elapsed.as_secs() / 60) as u64;
-                                if minutes > 0 {
-                                    let today = Local::now().format("%Y-%m-%d").to_string();
-                                    *history.entry(today).or_insert(0) += minutes;
-                                    if let Err(e) = _save_history(&history) {
-                                        eprintln!("Failed to save history: {}", e);
-                                    }
-                                }
-                                stopwatch_display = "00:00.00".to_string();
-                            } else {
-                                stopwatch_running = true;
-                                stopwatch_start = Instant::now();
-                            }
-                        }
-                        1 => {
+fn lock_path() -> Option<PathBuf> {
+    fokus_dir().map(|d| d.join("fokus.lock"))
+}
 
-                            if timer_done {
+/// Whether a process with this pid is running, or `None` where that can't be
+/// determined.
+#[cfg(unix)]
+fn pid_alive(pid: u32) -> Option<bool> {
+    use nix::{errno::Errno, sys::signal::kill, unistd::Pid};
 
-                                timer_display = format_duration(timer_total);
-                                timer_done = false;
-                            } else if timer_running {
-                                timer_running = false;
-                                timer_display = format_duration(timer_total);
-                            } else {
-                                timer_running = true;
-                                timer_start = Instant::now();
-                                timer_logged = false; 
-                            }
+    let pid = i32::try_from(pid).ok().filter(|p| *p > 0)?;
+    // Signal 0 only checks that the process exists and may be signalled.
+    match kill(Pid::from_raw(pid), None) {
+        Ok(()) | Err(Errno::EPERM) => Some(true),
+        Err(Errno::ESRCH) => Some(false),
+        Err(_) => None,
+    }
+}
+
+#[cfg(not(unix))]
+fn pid_alive(_pid: u32) -> Option<bool> {
+    None
+}
+
+/// Whether the process with this pid runs the same program as this one, for
+/// telling a live instance from a pid that was reused after a crash.
+#[cfg(target_os = "linux")]
+fn same_program(pid: u32) -> Option<bool> {
+    let comm = |p: &str| fs::read_to_string(format!("/proc/{}/comm", p)).ok();
+    Some(comm(&pid.to_string())?.trim() == comm("self")?.trim())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn same_program(_pid: u32) -> Option<bool> {
+    None
+}
+
+fn acquire_lock() -> io::Result<(fs::File, PathBuf)> {
+    if let Some(path) = lock_path() {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        if path.exists() {
+            if let Ok(s) = fs::read_to_string(&path) {
+                if let Ok(pid) = s.trim().parse::<u32>() {
+                    let alive = pid_alive(pid);
+                    if FORCE_LOCK.load(Ordering::Relaxed) {
+                        // Check once more before taking over, so that a live
+                        // instance is at least called out.
+                        if alive == Some(true) && pid_alive(pid) == Some(true) && same_program(pid) != Some(false) {
+                            eprintln!(
+                                "fokus: taking over {} from pid {}, which still looks like a running fokus",
+                                path.display(),
+                                pid,
+                            );
                         }
-                        _ => {}
-                    },
-                    KeyCode::Char('q') => {
-                        if stopwatch_running {
-                            let elapsed = stopwatch_start.elapsed();
-                            let minutes = (elapsed.as_secs() / 60) as u64;
-                            if minutes > 0 {
-                                let today = Local::now().format("%Y-%m-%d").to_string();
-                                *history.entry(today).or_insert(0) += minutes;
-                                if let Err(e) = _save_history(&history) {
-                                    eprintln!("Failed to save history: {}", e);
-                                }
+                        let _ = fs::remove_file(&path);
+                    } else {
+                        match alive {
+                            Some(true) => {
+                                return Err(io::Error::new(
+                                        io::ErrorKind::AlreadyExists,
+                                        format!(
+                                            "fokus is already running (pid {}, lock file {}). Quit it first, or start with --force if that process is not fokus.",
+                                            pid,
+                                            path.display(),
+                                        ),
+                                ));
+                            }
+                            Some(false) => {
+                                let _ = fs::remove_file(&path);
+                            }
+                            None => {
+                                return Err(io::Error::new(
+                                        io::ErrorKind::AlreadyExists,
+                                        format!(
+                                            "Another instance may be running (pid {}, lock file {}). If it is not, start with --force.",
+                                            pid,
+                                            path.display(),
+                                        ),
+                                ));
                             }
                         }
-                        break
                     }
-                    _ => {}
+                } else {
+                    let _ = fs::remove_file(&path);
                 }
+            } else {
+                let _ = fs::remove_file(&path);
             }
         }
+
+        let mut f = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        let pid = std::process::id();
+        use std::io::Write;
+        let _ = write!(f, "{}", pid);
+        Ok((f, path))
+    } else {
+        Err(io::Error::new(io::ErrorKind::NotFound, "Config directory could not be found."))
     }
+}
 
-    disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
-    terminal.show_cursor()?;
+/// The History page's table, split so the summary rows stay put while the
+/// days scroll underneath them.
+struct HistoryTable<'a> {
+    header: Table<'a>,
+    body: Table<'a>,
+    width: u16,
+    total_rows: usize,
+    visible_rows: usize,
+}
 
-    if let Err(e) = _save_history(&history) {
-        eprintln!("Failed to save history: {}", e);
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum HistoryView {
+    Days,
+    Tags,
+    Chart,
+    Heatmap,
+    Hours,
+}
+
+impl HistoryView {
+    fn next(self) -> HistoryView {
+        match self {
+            HistoryView::Days => HistoryView::Tags,
+            HistoryView::Tags => HistoryView::Chart,
+            HistoryView::Chart => HistoryView::Heatmap,
+            HistoryView::Heatmap => HistoryView::Hours,
+            HistoryView::Hours => HistoryView::Days,
+        }
     }
 
-    let _ = fs::remove_file(lock_path_buf);
-    drop(lock_file);
-    Ok(())
+    fn label(self) -> Option<&'static str> {
+        match self {
+            HistoryView::Days => None,
+            HistoryView::Tags => Some("by tag"),
+            HistoryView::Chart => Some("last 7 days"),
+            HistoryView::Heatmap => Some("this month"),
+            HistoryView::Hours => Some("by hour of day"),
+        }
+    }
 }
 
-fn format_duration(dur: Duration) -> String {
-    let mins = dur.as_secs() / 60;
-    let secs = dur.as_secs() % 60;
-    let centis = dur.subsec_millis() / 10;
-    format!("{:02}:{:02}.{:02}", mins, secs, centis)
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Phase {
+    Work,
+    ShortBreak,
+    LongBreak,
+}
+
+impl Phase {
+    fn label(self) -> &'static str {
+        match self {
+            Phase::Work => "Work",
+            Phase::ShortBreak => "Short Break",
+            Phase::LongBreak => "Long Break",
+        }
+    }
+
+    fn duration(self, config: &Config) -> Duration {
+        let minutes = match self {
+            Phase::Work => config.work_duration,
+            Phase::ShortBreak => config.short_break,
+            Phase::LongBreak => config.long_break,
+        };
+        Duration::from_secs(minutes * 60)
+    }
+
+    fn next(self, cycle: u64, cycles_before_long_break: u64) -> (Phase, u64) {
+        match self {
+            Phase::Work if cycle.is_multiple_of(cycles_before_long_break) => (Phase::LongBreak, cycle),
+            Phase::Work => (Phase::ShortBreak, cycle),
+            Phase::ShortBreak | Phase::LongBreak => (Phase::Work, cycle + 1),
+        }
+    }
+}
+
+struct StopwatchState {
+    start: Instant,
+    running: bool,
+    accumulated: Duration,
+    // Seconds of `accumulated` and the current run already written by autosave.
+    flushed: u64,
+}
+
+impl StopwatchState {
+    fn new() -> StopwatchState {
+        StopwatchState {
+            start: Instant::now(),
+            running: false,
+            accumulated: Duration::ZERO,
+            flushed: 0,
+        }
+    }
+
+    fn elapsed(&self, now: Instant) -> Duration {
+        if self.running {
+            self.accumulated + now.saturating_duration_since(self.start)
+        } else {
+            self.accumulated
+        }
+    }
+
+    /// Whether nothing has been counted since the last reset.
+    fn is_idle(&self) -> bool {
+        !self.running && self.accumulated.is_zero()
+    }
+
+    fn toggle(&mut self, now: Instant) {
+        if self.running {
+            self.accumulated += now.saturating_duration_since(self.start);
+            self.running = false;
+        } else {
+            self.start = now;
+            self.running = true;
+        }
+    }
+
+    /// Seconds counted that have not been logged yet.
+    fn unlogged_seconds(&self, now: Instant) -> u64 {
+        self.elapsed(now).as_secs().saturating_sub(self.flushed)
+    }
+
+    /// Marks everything counted so far as logged, returning the seconds that
+    /// were not yet.
+    fn flush(&mut self, now: Instant) -> u64 {
+        let secs = self.unlogged_seconds(now);
+        self.flushed += secs;
+        secs
+    }
+
+    /// Stops and clears the stopwatch, returning the seconds it had counted
+    /// since the last flush.
+    fn reset(&mut self, now: Instant) -> u64 {
+        let secs = self.unlogged_seconds(now);
+        self.running = false;
+        self.accumulated = Duration::ZERO;
+        self.flushed = 0;
+        secs
+    }
+}
+
+struct TimerState {
+    total: Duration,
+    start: Instant,
+    running: bool,
+    logged: bool,
+    done: bool,
+    last_started_total: Duration,
+    /// Time left on an interval that is paused rather than stopped.
+    remaining_at_pause: Option<Duration>,
+    /// Seconds of the current interval already logged at midnight.
+    flushed: u64,
+}
+
+impl TimerState {
+    fn new(total: Duration) -> TimerState {
+        TimerState {
+            total,
+            start: Instant::now(),
+            running: false,
+            logged: false,
+            done: false,
+            last_started_total: total,
+            remaining_at_pause: None,
+            flushed: 0,
+        }
+    }
+
+    fn elapsed(&self, now: Instant) -> Duration {
+        match self.remaining_at_pause {
+            Some(remaining) => self.total.saturating_sub(remaining),
+            None if self.running => now.saturating_duration_since(self.start).min(self.total),
+            None => Duration::ZERO,
+        }
+    }
+
+    fn remaining(&self, now: Instant) -> Duration {
+        if self.done {
+            Duration::ZERO
+        } else {
+            self.total - self.elapsed(now)
+        }
+    }
+
+    fn is_paused(&self) -> bool {
+        self.remaining_at_pause.is_some()
+    }
+
+    /// Neither running nor paused, so the duration can be changed.
+    fn is_idle(&self) -> bool {
+        !self.running && !self.is_paused()
+    }
+
+    fn set_total(&mut self, total: Duration) {
+        self.total = total;
+        self.done = false;
+    }
+
+    fn start(&mut self, now: Instant) {
+        self.running = true;
+        self.start = now;
+        self.logged = false;
+        self.last_started_total = self.total;
+        self.remaining_at_pause = None;
+        self.flushed = 0;
+    }
+
+    fn stop(&mut self) {
+        self.running = false;
+        self.remaining_at_pause = None;
+    }
+
+    /// Freezes a running interval where it is, or picks a paused one back up.
+    fn toggle_pause(&mut self, now: Instant) {
+        if self.running {
+            self.remaining_at_pause = Some(self.remaining(now));
+            self.running = false;
+        } else if let Some(remaining) = self.remaining_at_pause.take() {
+            self.resume(self.total, remaining, now);
+        }
+    }
+
+    /// Starts a fresh interval without stopping, as when a Pomodoro phase ends.
+    fn restart(&mut self, total: Duration, now: Instant) {
+        self.set_total(total);
+        self.start = now;
+        self.running = true;
+        self.logged = false;
+        self.flushed = 0;
+    }
+
+    /// Continues a run that had `remaining` left of `total` when it was checkpointed.
+    fn resume(&mut self, total: Duration, remaining: Duration, now: Instant) {
+        self.total = total;
+        self.start = now.checked_sub(total.saturating_sub(remaining)).unwrap_or(now);
+        self.running = true;
+    }
+
+    /// Seconds run so far by an interval that has not finished, less any
+    /// flushed already; zero once its completion has been logged.
+    fn partial_seconds(&self, now: Instant) -> u64 {
+        if (self.running || self.is_paused()) && !self.logged {
+            self.elapsed(now).as_secs().saturating_sub(self.flushed)
+        } else {
+            0
+        }
+    }
+
+    /// Marks the time run so far as logged, returning the seconds that were not yet.
+    fn flush(&mut self, now: Instant) -> u64 {
+        let secs = self.partial_seconds(now);
+        self.flushed += secs;
+        secs
+    }
+
+    /// Advances the timer, returning true exactly once when it runs out.
+    fn tick(&mut self, now: Instant) -> bool {
+        if self.running && !self.logged && self.elapsed(now) >= self.total {
+            self.running = false;
+            self.done = true;
+            self.logged = true;
+            return true;
+        }
+        false
+    }
+}
+
+/// What quitting adds to the history for the stopwatch, which is reset, and
+/// the timer: the part of each not logged yet, passed through `settle` along
+/// with what already is. A break interval adds nothing.
+fn quit_seconds(
+    stopwatch: &mut StopwatchState,
+    timer: &TimerState,
+    phase: Phase,
+    now: Instant,
+    settle: impl Fn(u64, u64) -> u64,
+) -> (u64, u64) {
+    let stopwatch_secs = settle(stopwatch.flushed, stopwatch.reset(now));
+    let timer_secs = if phase == Phase::Work {
+        settle(timer.flushed, timer.partial_seconds(now))
+    } else {
+        0
+    };
+    (stopwatch_secs, timer_secs)
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Checkpoint {
+    total_secs: u64,
+    remaining_secs: u64,
+    saved_at: i64,
+}
+
+fn sessions_path() -> Option<PathBuf> {
+    fokus_dir().map(|d| d.join("sessions.json"))
+}
+
+/// One logged session. sessions.json holds one of these per line, appended as
+/// sessions end; the daily totals in history.json stay the source for the
+/// History page.
+#[derive(Serialize, Deserialize, Debug)]
+struct SessionRecord {
+    ended_at: String,
+    seconds: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    note: Option<String>,
+    // Whether this was a focus timer that ran all the way down, i.e. a
+    // pomodoro. Absent (false) for everything else.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    completed: bool,
+}
+
+impl SessionRecord {
+    fn new(tag: Option<&str>, seconds: u64, note: Option<String>) -> SessionRecord {
+        SessionRecord {
+            ended_at: Local::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, false),
+            seconds,
+            tag: tag.map(str::to_string),
+            note,
+            completed: false,
+        }
+    }
+
+    /// A focus timer that ran all the way down at `end`.
+    fn pomodoro(tag: Option<&str>, seconds: u64, end: DateTime<Local>) -> SessionRecord {
+        SessionRecord {
+            ended_at: end.to_rfc3339_opts(chrono::SecondsFormat::Secs, false),
+            completed: true,
+            ..SessionRecord::new(tag, seconds, None)
+        }
+    }
+
+    /// The local day it ended on.
+    fn ended_on(&self) -> Option<NaiveDate> {
+        DateTime::parse_from_rfc3339(&self.ended_at).ok().map(|t| t.with_timezone(&Local).date_naive())
+    }
+}
+
+/// Focus timers completed on `day`, by the day they finished.
+fn sessions_on(sessions: &[SessionRecord], day: NaiveDate) -> u64 {
+    sessions.iter()
+        .filter(|s| s.ended_on() == Some(day))
+        .count() as u64
+}
+
+fn pomodoros_on(sessions: &[SessionRecord], day: NaiveDate) -> u64 {
+    sessions.iter()
+        .filter(|s| s.completed && s.ended_on() == Some(day))
+        .count() as u64
+}
+
+/// The pomodoros finished on one day, for the count below the timer. It
+/// starts over on the first one of a new day.
+struct PomodoroCount {
+    day: NaiveDate,
+    count: u64,
+}
+
+impl PomodoroCount {
+    fn on(&self, day: NaiveDate) -> u64 {
+        if day == self.day { self.count } else { 0 }
+    }
+
+    /// Counts one finished on `day`, returning that day's count.
+    fn add(&mut self, day: NaiveDate) -> u64 {
+        if day != self.day {
+            self.day = day;
+            self.count = 0;
+        }
+        self.count += 1;
+        self.count
+    }
+
+    fn remove(&mut self, day: NaiveDate) {
+        if day == self.day {
+            self.count = self.count.saturating_sub(1);
+        }
+    }
+}
+
+/// Logs a focus interval that ran all the way down at `end` and counts it as
+/// a pomodoro. Returns its line for sessions.json, and whether it was the one
+/// that reached `daily_goal`.
+fn finish_pomodoro(
+    store: &mut dyn HistoryStore,
+    undo: &mut Vec<LoggedSession>,
+    pomodoros: &mut PomodoroCount,
+    timer: &TimerState,
+    tag: Option<&str>,
+    daily_goal: u64,
+    end: DateTime<Local>,
+) -> (SessionRecord, bool) {
+    let secs = timer.total.as_secs().saturating_sub(timer.flushed);
+    log_seconds_until(store, end.naive_local(), tag, secs);
+    let session = SessionRecord::pomodoro(tag, timer.total.as_secs(), end);
+    push_undo(undo, &session, secs);
+    let count = pomodoros.add(end.date_naive());
+    (session, count == daily_goal)
+}
+
+fn append_session(record: &SessionRecord) -> io::Result<()> {
+    use std::io::Write;
+
+    if !persisting() {
+        return Ok(());
+    }
+
+    let path = sessions_path()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Config directory not found"))?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let line = serde_json::to_string(record).map_err(io::Error::other)?;
+    let mut f = fs::OpenOptions::new().create(true).append(true).open(&path)?;
+    writeln!(f, "{}", line)?;
+    SESSIONS_WRITTEN.fetch_add(1, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Every session in sessions.json, oldest first. Lines that don't parse are
+/// skipped.
+fn read_sessions() -> Vec<SessionRecord> {
+    sessions_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .map(|s| s.lines().filter_map(|line| serde_json::from_str(line).ok()).collect())
+        .unwrap_or_default()
+}
+
+/// Focus minutes per local hour of day. A session counts entirely towards the
+/// hour it started in (its end time minus its length), even if it ran past
+/// the end of that hour.
+fn hour_histogram(sessions: &[SessionRecord]) -> [u64; 24] {
+    let mut seconds = [0u64; 24];
+    for session in sessions {
+        let Ok(ended) = DateTime::parse_from_rfc3339(&session.ended_at) else {
+            continue;
+        };
+        let started = ended.with_timezone(&Local) - chrono::Duration::seconds(session.seconds as i64);
+        seconds[started.hour() as usize] += session.seconds;
+    }
+    seconds.map(|s| s / 60)
+}
+
+/// sessions.json without the last line written for `session`, or `None` if
+/// there is no such line.
+fn forget_session(contents: &str, session: &LoggedSession) -> Option<String> {
+    let lines: Vec<&str> = contents.lines().collect();
+    let i = lines.iter().rposition(|line| serde_json::from_str(line).is_ok_and(|r| session.wrote(&r)))?;
+    Some(lines.iter().enumerate()
+        .filter(|(j, _line)| *j != i)
+        .map(|(_j, line)| format!("{}\n", line))
+        .collect())
+}
+
+fn remove_session(session: &LoggedSession) {
+    if !persisting() || session.ended_at.is_none() {
+        return;
+    }
+    let Some(path) = sessions_path() else {
+        return;
+    };
+    let removed = fs::read_to_string(&path).map(|s| forget_session(&s, session));
+    match removed {
+        Ok(Some(kept)) => match write_atomic(&path, &kept) {
+            Ok(()) => {
+                SESSIONS_WRITTEN.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(e) => eprintln!("Failed to save sessions: {}", e),
+        },
+        Ok(None) => {}
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => eprintln!("Failed to read sessions: {}", e),
+    }
+}
+
+fn save_session(record: &SessionRecord) {
+    if record.seconds > 0 && let Err(e) = append_session(record) {
+        eprintln!("Failed to save session: {}", e);
+    }
+}
+
+fn checkpoint_path() -> Option<PathBuf> {
+    fokus_dir().map(|d| d.join("checkpoint.json"))
+}
+
+fn save_checkpoint(total: Duration, remaining: Duration) -> io::Result<()> {
+    if !persisting() {
+        return Ok(());
+    }
+    if let Some(path) = checkpoint_path() {
+        let cp = Checkpoint {
+            total_secs: total.as_secs(),
+            remaining_secs: remaining.as_secs(),
+            saved_at: Local::now().timestamp(),
+        };
+        let s = serde_json::to_string(&cp).map_err(io::Error::other)?;
+        fs::write(path, s)
+    } else {
+        Err(io::Error::new(io::ErrorKind::NotFound, "Config directory not found"))
+    }
+}
+
+fn load_checkpoint(max_age: Duration) -> Option<(Duration, Duration)> {
+    let path = checkpoint_path()?;
+    let s = fs::read_to_string(&path).ok()?;
+    let cp = serde_json::from_str::<Checkpoint>(&s).ok()?;
+
+    let age = u64::try_from(Local::now().timestamp() - cp.saved_at).ok()?;
+    if age > max_age.as_secs() || age >= cp.remaining_secs || cp.remaining_secs > cp.total_secs {
+        clear_checkpoint();
+        return None;
+    }
+
+    Some((
+        Duration::from_secs(cp.total_secs),
+        Duration::from_secs(cp.remaining_secs - age),
+    ))
+}
+
+fn clear_checkpoint() {
+    if persisting() && let Some(path) = checkpoint_path() {
+        let _ = fs::remove_file(path);
+    }
+}
+
+fn state_path() -> Option<PathBuf> {
+    fokus_dir().map(|d| d.join("state.json"))
+}
+
+/// Bits of the UI carried over from one run to the next.
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
+struct SavedState {
+    last_timer_secs: Option<u64>,
+    last_page: Option<usize>,
+}
+
+fn load_state() -> SavedState {
+    state_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save_state(state: &SavedState) -> io::Result<()> {
+    if !persisting() {
+        return Ok(());
+    }
+    let path = state_path()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Config directory not found"))?;
+    let s = serde_json::to_string(state).map_err(io::Error::other)?;
+    fs::write(path, s)
+}
+
+fn status_path() -> Option<PathBuf> {
+    fokus_dir().map(|d| d.join("status.json"))
+}
+
+/// Rewritten every second, so unlike the history this skips the fsync; the
+/// rename alone keeps readers from seeing half a file.
+fn write_status(status: &serde_json::Value) -> io::Result<()> {
+    if !persisting() {
+        return Ok(());
+    }
+    let path = status_path()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Config directory not found"))?;
+    let tmp = path.with_file_name("status.json.tmp");
+    fs::write(&tmp, status.to_string())?;
+    fs::rename(&tmp, path)
+}
+
+fn clear_status() {
+    if persisting() && let Some(path) = status_path() {
+        let _ = fs::remove_file(path);
+    }
+}
+
+/// Focus seconds on the clock that aren't in the history yet.
+fn in_progress_seconds(stopwatch: &StopwatchState, timer: &TimerState, phase: Phase, now: Instant) -> u64 {
+    if !stopwatch.is_idle() {
+        stopwatch.unlogged_seconds(now)
+    } else if !timer.is_idle() && phase == Phase::Work {
+        timer.partial_seconds(now)
+    } else {
+        0
+    }
+}
+
+/// Opens the config file in $EDITOR (or vi, or nano), creating it first, and
+/// checks that what was saved still loads.
+fn edit_config() -> io::Result<()> {
+    let path = Config::config_path()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Config directory not found"))?;
+    // Only a missing file is written here: one that is already there goes to
+    // the editor as it is, broken or not, so nothing typed in it is lost.
+    if !path.exists() {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, Config::default_toml())?;
+    }
+
+    let mut editors: Vec<String> = Vec::new();
+    if let Ok(editor) = std::env::var("EDITOR") && !editor.trim().is_empty() {
+        editors.push(editor);
+    }
+    editors.extend(["vi".to_string(), "nano".to_string()]);
+    let mut status = None;
+    for editor in &editors {
+        let mut words = editor.split_whitespace();
+        let Some(program) = words.next() else { continue };
+        match std::process::Command::new(program).args(words).arg(&path).status() {
+            Ok(s) => {
+                status = Some(s);
+                break;
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    match status {
+        None => return Err(io::Error::new(io::ErrorKind::NotFound, "No editor found; set $EDITOR")),
+        Some(s) if !s.success() => return Err(io::Error::other(format!("The editor exited with {}", s))),
+        Some(_) => {}
+    }
+
+    let s = fs::read_to_string(&path)?;
+    let problem = match toml::from_str::<Config>(&s) {
+        Err(e) => Some(e.to_string()),
+        Ok(cfg) if !(CONFIG_TIMER_MIN..=CONFIG_TIMER_MAX).contains(&cfg.default_timer_duration) => Some(format!(
+            "default_timer_duration must be between {} and {}",
+            CONFIG_TIMER_MIN, CONFIG_TIMER_MAX,
+        )),
+        Ok(_) => None,
+    };
+    match problem {
+        Some(problem) => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "{} has a problem and will be replaced with the defaults when fokus next starts; \
+                 run fokus --edit-config again to fix it:\n{}",
+                path.display(),
+                problem.trim_end(),
+            ),
+        )),
+        None => {
+            println!("{} is valid", path.display());
+            Ok(())
+        }
+    }
+}
+
+/// Commands whose whole point is to change files on disk, which --no-save
+/// would quietly turn into claims about changes that never happened.
+fn writes_to_disk(command: &str) -> bool {
+    matches!(
+        command,
+        "--edit-config" | "--reset-today" | "--clear-history" | "--add" | "--import" | "--restore-backup"
+    )
+}
+
+fn print_help() {
+    let shown = |p: Option<PathBuf>| p.map_or_else(|| "(no config directory found)".to_string(), |p| p.display().to_string());
+    println!(
+        "fokus {}\n\
+         A terminal focus timer and stopwatch with daily logging\n\n\
+         Usage: fokus [--resume] [--no-save] [--no-lock] [--force]\n       fokus [--force] <command>\n\n\
+         Options:\n  \
+           --resume                         Continue a timer that was running when fokus last quit\n  \
+           --no-save                        Don't write anything to the history (TUI only)\n  \
+           --no-lock                        Allow several instances at once, merging their history\n  \
+           --force                          Take the lock over from an instance that crashed\n  \
+           -h, --help                       Show this help\n  \
+           -V, --version                    Show the version\n\n\
+         Commands:\n  \
+           --today [--json]                 Print the minutes focused today\n  \
+           --edit-config                    Open the config file in $EDITOR and check it afterwards\n  \
+           --add <minutes> [--date D]       Log minutes by hand, today or on YYYY-MM-DD\n  \
+           --reset-today                    Clear the minutes logged today\n  \
+           --clear-history [--yes]          Back up the history, then empty it\n  \
+           --year-summary [YEAR] [--json]   Summarize a year, the current one by default\n  \
+           --import <history.json>          Add another history file's minutes to this one\n  \
+           --export-csv <path|->            Export the daily history as CSV\n  \
+           --export-svg <path> [--last N]   Export a chart of the last N days (30) as SVG\n  \
+           --list-backups                   List history backups\n  \
+           --restore-backup <name>          Restore a history backup\n\n\
+         Files:\n  \
+           config   {}\n  \
+           history  {}\n\n\
+         The config directory can be moved with FOKUS_CONFIG_DIR, and the two files\n\
+         with FOKUS_CONFIG and FOKUS_HISTORY. Key bindings are listed in the TUI with [?].",
+        env!("CARGO_PKG_VERSION"),
+        shown(Config::config_path()),
+        shown(history_path()),
+    );
+}
+
+fn with_lock<T>(f: impl FnOnce() -> io::Result<T>) -> io::Result<T> {
+    let (lock_file, lock_path_buf) = acquire_lock()?;
+    let result = f();
+    let _ = fs::remove_file(lock_path_buf);
+    drop(lock_file);
+    result
+}
+
+fn main() -> io::Result<()> {
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|a| a == "--help" || a == "-h") {
+        print_help();
+        return Ok(());
+    }
+    if args.iter().any(|a| a == "--version" || a == "-V") {
+        println!("fokus {}", env!("CARGO_PKG_VERSION"));
+        return Ok(());
+    }
+    let resume = args.iter().any(|a| a == "--resume");
+    let no_save = args.iter().any(|a| a == "--no-save");
+    let no_lock = args.iter().any(|a| a == "--no-lock");
+    if no_save {
+        PERSIST.store(false, Ordering::Relaxed);
+    }
+    if args.iter().any(|a| a == "--force") {
+        FORCE_LOCK.store(true, Ordering::Relaxed);
+    }
+
+    // Options may come before the command (`fokus --force --reset-today`), so
+    // the first argument that names one is it, and its values follow it.
+    const COMMANDS: [&str; 11] = [
+        "--list-backups", "--edit-config", "--reset-today", "--clear-history", "--today", "--export-svg",
+        "--export-csv", "--add", "--year-summary", "--import", "--restore-backup",
+    ];
+    let command: &[String] = match args.iter().position(|a| COMMANDS.contains(&a.as_str())) {
+        Some(i) => &args[i..],
+        None => &[],
+    };
+    if no_save && let Some(cmd) = command.first().filter(|cmd| writes_to_disk(cmd)) {
+        eprintln!("fokus: --no-save can't be used with {}, which writes to disk", cmd);
+        std::process::exit(1);
+    }
+    let cli_result = match command.first().map(String::as_str) {
+        Some("--list-backups") => Some(list_backups()),
+        Some("--edit-config") => Some(edit_config()),
+        Some("--reset-today") => Some(reset_today()),
+        Some("--clear-history") => Some(clear_history(args.iter().any(|a| a == "--yes"))),
+        Some("--today") => Some(JsonHistory::read().and_then(|store| print_today(&store, args.iter().any(|a| a == "--json")))),
+        Some("--export-svg") => match command.get(1) {
+            Some(path) => {
+                let days = match args.iter().position(|a| a == "--last") {
+                    Some(i) => parse_last(args.get(i + 1).map(String::as_str)),
+                    None => Some(30),
+                };
+                match days {
+                    Some(days) => Some(export_svg(Path::new(path), days)),
+                    None => Some(Err(io::Error::new(
+                                io::ErrorKind::InvalidInput,
+                                format!("--last expects a number of days from 1 to {}", SVG_MAX_DAYS),
+                    ))),
+                }
+            }
+            None => Some(Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "Usage: fokus --export-svg <path> [--last <days>]",
+            ))),
+        },
+        Some("--export-csv") => match command.get(1) {
+            Some(path) => Some(export_csv(path)),
+            None => Some(Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "Usage: fokus --export-csv <path|->",
+            ))),
+        },
+        Some("--add") => {
+            let minutes = command.get(1).and_then(|m| m.parse::<u64>().ok()).filter(|m| *m > 0);
+            let date = match args.iter().position(|a| a == "--date") {
+                Some(i) => args.get(i + 1).and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok()),
+                None => Some(Local::now().date_naive()),
+            };
+            match (minutes, date) {
+                (Some(minutes), Some(date)) => Some(add_minutes(minutes, date)),
+                _ => Some(Err(io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            "Usage: fokus --add <minutes> [--date YYYY-MM-DD]",
+                ))),
+            }
+        }
+        Some("--year-summary") => {
+            let json = args.iter().any(|a| a == "--json");
+            match command.get(1).filter(|a| *a != "--json") {
+                None => Some(JsonHistory::read().and_then(|store| print_year_summary(&store, Local::now().year(), json))),
+                Some(year) => match year.parse::<i32>() {
+                    Ok(year) => Some(JsonHistory::read().and_then(|store| print_year_summary(&store, year, json))),
+                    Err(_) => Some(Err(io::Error::new(
+                                io::ErrorKind::InvalidInput,
+                                "Usage: fokus --year-summary [YEAR] [--json]",
+                    ))),
+                },
+            }
+        }
+        Some("--import") => match command.get(1) {
+            Some(path) => Some(import_history(Path::new(path))),
+            None => Some(Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "Usage: fokus --import <history.json>",
+            ))),
+        },
+        Some("--restore-backup") => match command.get(1) {
+            Some(name) => Some(restore_backup(name)),
+            None => Some(Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "Usage: fokus --restore-backup <name>",
+            ))),
+        },
+        _ => None,
+    };
+    if let Some(result) = cli_result {
+        if let Err(e) = result {
+            eprintln!("fokus: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    let (config, config_error) = Config::load_or_create();
+
+    // Another instance holding the lock is fatal; a lock that can't be
+    // written at all means nothing else can be either, so fokus carries on
+    // without one and without saving. --no-lock skips it altogether.
+    let mut readonly_reason: Option<String> = None;
+    let lock = if no_lock {
+        None
+    } else {
+        match acquire_lock() {
+            Ok(t) => Some(t),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                eprintln!("fokus: {}", e);
+                std::process::exit(1);
+            }
+            Err(e) => {
+                readonly_reason = Some(e.to_string());
+                None
+            }
+        }
+    };
+    let release_lock = |lock: Option<(fs::File, PathBuf)>| {
+        if let Some((file, path)) = lock {
+            let _ = fs::remove_file(path);
+            drop(file);
+        }
+    };
+
+    let extra = Duration::from_secs(config.timer_step_minutes * 60);
+    let coarse = Duration::from_secs(config.timer_coarse_step_minutes * 60);
+    let timer_max = Duration::from_secs(60*999);
+    let timer_min = Duration::from_secs(60);
+
+    let remembered = load_state().last_timer_secs.filter(|_| config.remember_last_timer);
+    let timer_total = if config.pomodoro_enabled {
+        Phase::Work.duration(&config)
+    } else if let Some(secs) = remembered {
+        Duration::from_secs(secs).clamp(timer_min, timer_max)
+    } else {
+        Duration::from_secs(config.default_timer_duration * 60)
+    };
+    let mut phase = Phase::Work;
+    let mut cycle: u64 = 1;
+
+    let loaded = if no_save || readonly_reason.is_some() {
+        read_history()
+    } else {
+        // If the history exists but can't be written, it's still worth
+        // showing; a file that can't be read at all stays fatal.
+        load_or_create_history().or_else(|e| {
+            let h = read_history()?;
+            readonly_reason = Some(e.to_string());
+            Ok(h)
+        })
+    };
+    let mut store: Box<dyn HistoryStore> = match loaded {
+        Ok((days, tags)) => Box::new(JsonHistory { days, tags }),
+        Err(e) => {
+            release_lock(lock);
+            eprintln!("fokus: {}", e);
+            std::process::exit(1);
+        }
+    };
+    if readonly_reason.is_some() {
+        PERSIST.store(false, Ordering::Relaxed);
+    }
+    let persist_note = if no_save {
+        Some("not saving (--no-save)".to_string())
+    } else if let Some(e) = readonly_reason {
+        Some(format!("history read-only, not saving: {}", e))
+    } else if no_lock {
+        Some("not locked (--no-lock): changes are merged into the history on save".to_string())
+    } else {
+        None
+    };
+    let mut status_message: Option<(String, Instant)> =
+        config_error.map(|e| (format!("Could not write the config file: {}", e), Instant::now()));
+
+    enable_raw_mode()?;
+    let mut stdout = stdout();
+    execute!(stdout, EnterAlternateScreen)?; 
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+
+    let header_pages = ["< Page 1 of 3 >", "< Page 2 of 3 >", "< Page 3 of 3 >"];
+    let mut header_page_index = match load_state().last_page {
+        Some(page) if config.remember_last_page => page.min(header_pages.len() - 1),
+        _ => config.default_start_page,
+    };
+
+    let mut stopwatch = StopwatchState::new();
+
+    let mut timer = TimerState::new(timer_total);
+
+    let mut history_offset = 0; 
+    // The highlighted row, counted from the top of the whole list.
+    let mut history_selected: usize = 0;
+    let mut selected_day: Option<String> = None;
+    let mut delete_prompt: Option<String> = None;
+    let mut history_page_rows: usize = 1;
+    let mut history_range: Option<(NaiveDate, NaiveDate)> = None;
+    let mut history_view = HistoryView::Days;
+    let mut hour_minutes = [0u64; 24];
+    let mut range_input: Option<String> = None;
+    // The day being moved and the date typed so far.
+    let mut move_input: Option<(String, String)> = None;
+    let mut history_search = String::new();
+    let mut search_input = false;
+    let mut duration_input: Option<String> = None;
+    // Clock time typed into [i], with the total it resolved to.
+    let mut timer_target: Option<(NaiveTime, Duration)> = None;
+    let mut active_tag: Option<usize> = None;
+    let mut active_preset: Option<usize> = None;
+    let mut last_input = Instant::now();
+    let mut current_day = Local::now().date_naive();
+    let mut idle_paused = false;
+    // When the break shown on the stopwatch page after a timer began.
+    let mut break_started: Option<Instant> = None;
+    // Stretch reminders shown since the stopwatch was last started.
+    let mut stretch_reminders = 0;
+    let mut quit_prompt = false;
+    let mut log_on_quit = config.quit_behavior != "discard";
+    let mut show_help = false;
+    let mut undo: Vec<LoggedSession> = Vec::new();
+    let mut note_input: Option<(String, SessionRecord)> = None;
+    // The unlogged part of a session that is being stopped, given the
+    // `logged` seconds of it already saved: nothing if the whole session is
+    // under the floor, otherwise what makes the total come out rounded.
+    let settle = |logged: u64, secs: u64| {
+        if logged + secs < config.min_session_seconds {
+            0
+        } else {
+            round_session(logged + secs, &config.rounding).saturating_sub(logged)
+        }
+    };
+    let partial = |timer: &TimerState, phase: Phase| {
+        if config.log_partial_timer && phase == Phase::Work {
+            settle(timer.flushed, timer.partial_seconds(Instant::now()))
+        } else {
+            0
+        }
+    };
+    let tag_name = |i: Option<usize>| i.and_then(|i| config.tags.get(i)).map(String::as_str);
+
+    let keymap = Keymap::from_config(&config.keybindings);
+    let mut pending_key: Option<KeyCode> = None;
+    let palette = config.theme.palette();
+
+    let mut clipboard: Option<arboard::Clipboard> = None;
+
+    let mut checkpoint_saved = Instant::now();
+    let started = Instant::now();
+    let mut status_written: Option<Instant> = None;
+
+    let today_key = Local::now().format("%Y-%m-%d").to_string();
+    let mut goal_notified = goal_progress(store.total_for(&today_key) / 60, config.daily_goal)
+        .is_none_or(|(_text, met)| met);
+    // The best day before today, and whether today has already beaten it.
+    let mut record_to_beat = record_day(store.dated().into_iter().filter(|(d, _secs)| *d != Local::now().date_naive()));
+    let mut pomodoros = {
+        let today = Local::now().date_naive();
+        PomodoroCount { day: today, count: pomodoros_on(&read_sessions(), today) }
+    };
+    // Re-counted from sessions.json only when a session was written or the
+    // day changed since.
+    let mut sessions_today = 0;
+    let mut sessions_counted: Option<(u64, NaiveDate)> = None;
+    let mut record_notified = record_to_beat.is_none_or(|(_d, secs)| store.total_for(&today_key) > secs);
+    let quotes: Vec<&str> = if config.quotes.is_empty() {
+        DEFAULT_QUOTES.to_vec()
+    } else {
+        config.quotes.iter().map(String::as_str).collect()
+    };
+    // Picked again only on a page change or a finished interval, never per frame.
+    let mut quote = quote_seed() as usize % quotes.len();
+    let mut quote_page = header_page_index;
+
+    if resume {
+        let max_age = Duration::from_secs(config.resume_max_age_minutes * 60);
+        if let Some((total, remaining)) = load_checkpoint(max_age) {
+            timer.resume(total, remaining, Instant::now());
+            header_page_index = 1;
+        }
+    }
+
+    terminal.clear()?;
+    terminal.hide_cursor()?;
+
+    loop {
+
+        let area = terminal.size()?;
+        let middle_height = area.height / 2; 
+        let visible_height = (middle_height as usize).saturating_sub(2);
+
+        let now = Instant::now();
+        let quiet = config.quiet_hours.is_quiet(Local::now().time());
+        let counted = (SESSIONS_WRITTEN.load(Ordering::Relaxed), Local::now().date_naive());
+        if sessions_counted != Some(counted) {
+            sessions_today = sessions_on(&read_sessions(), counted.1);
+            sessions_counted = Some(counted);
+        }
+        if timer.tick(now) {
+            quote = next_quote(quote, quotes.len(), quote_seed());
+            if phase == Phase::Work {
+                let (session, reached_goal) = finish_pomodoro(
+                    store.as_mut(),
+                    &mut undo,
+                    &mut pomodoros,
+                    &timer,
+                    tag_name(active_tag),
+                    config.daily_pomodoro_goal,
+                    Local::now(),
+                );
+                if reached_goal && config.notifications_enabled && !quiet {
+                    notify("Pomodoro goal reached", &format!("{} pomodoros today", config.daily_pomodoro_goal));
+                }
+                if config.session_notes {
+                    if let Some((_buf, pending)) = note_input.replace((String::new(), session)) {
+                        save_session(&pending);
+                    }
+                } else {
+                    save_session(&session);
+                }
+            }
+            if config.notifications_enabled && !quiet {
+                let minutes = timer.total.as_secs() / 60;
+                match phase {
+                    Phase::Work => notify(
+                        "Focus session complete",
+                        &format!("{} minutes logged", minutes),
+                    ),
+                    _ => notify("Break is over", "Time to focus again"),
+                }
+            }
+            if config.sound_enabled && !quiet {
+                play_sound(config.sound_command.as_deref());
+            }
+            clear_checkpoint();
+
+            if config.pomodoro_enabled {
+                (phase, cycle) = phase.next(cycle, config.cycles_before_long_break);
+                timer.restart(phase.duration(&config), now);
+            } else if config.auto_break_stopwatch && stopwatch.is_idle() {
+                break_started = Some(now);
+                header_page_index = 0;
+            }
+        }
+        if config.autosave_minutes > 0
+            && stopwatch.running
+            && stopwatch.unlogged_seconds(now) >= config.autosave_minutes * 60
+        {
+            let secs = stopwatch.flush(now);
+            log_seconds(store.as_mut(), tag_name(active_tag), secs);
+        }
+        if stopwatch.running
+            && idle_for(last_input, now, config.idle_pause_minutes)
+        {
+            // Paused as of the last key press, so the time walked away isn't
+            // counted; autosave may already have logged some of it.
+            stopwatch.toggle(last_input.max(stopwatch.start));
+            let over = stopwatch.flushed.saturating_sub(stopwatch.elapsed(now).as_secs());
+            if over > 0 {
+                stopwatch.flushed -= over;
+                undo_session(
+                    store.as_mut(),
+                    &LoggedSession {
+                        day: Local::now().format("%Y-%m-%d").to_string(),
+                        tag: tag_name(active_tag).map(str::to_string),
+                        seconds: over,
+                        ended_at: None,
+                        completed: false,
+                    },
+                );
+            }
+            idle_paused = true;
+        }
+        // Left alone with nothing counting, quit the usual way so the lock is
+        // released; a running or paused timer keeps fokus open.
+        if !stopwatch.running
+            && timer.is_idle()
+            && break_started.is_none()
+            && idle_for(last_input, now, config.auto_quit_idle_minutes)
+        {
+            break;
+        }
+        if !stopwatch.running {
+            stretch_reminders = 0;
+        } else {
+            let run_minutes = now.saturating_duration_since(stopwatch.start).as_secs() / 60;
+            if let Some(due) = run_minutes.checked_div(config.stretch_reminder_minutes)
+                && due > stretch_reminders
+            {
+                stretch_reminders = due;
+                let text = format!("You've focused {} minutes - consider a stretch", run_minutes);
+                if config.notifications_enabled && !quiet {
+                    notify("Time for a stretch", &text);
+                }
+                if config.sound_enabled && !quiet {
+                    play_sound(config.sound_command.as_deref());
+                }
+                status_message = Some((text, Instant::now()));
+            }
+        }
+        // A session running over midnight counts towards both days. Logging
+        // already splits at midnight, but only by counting back from when it
+        // is logged, so flush what is on the clock now in case it is paused
+        // before it ends. (A focus interval is only flushed like this when
+        // partial intervals are logged at all; otherwise it is split when it
+        // finishes.)
+        let today = Local::now().date_naive();
+        if today != current_day {
+            let mut secs = stopwatch.flush(now);
+            if config.log_partial_timer && phase == Phase::Work {
+                secs += timer.flush(now);
+            }
+            log_seconds(store.as_mut(), tag_name(active_tag), secs);
+            current_day = today;
+            let today_key = today.format("%Y-%m-%d").to_string();
+            record_to_beat = record_day(store.dated().into_iter().filter(|(d, _secs)| *d != today));
+            record_notified = record_to_beat.is_none_or(|(_d, secs)| store.total_for(&today_key) > secs);
+        }
+
+        let stopwatch_display = match break_started {
+            Some(start) => format_duration(now.saturating_duration_since(start)),
+            None => format_duration(stopwatch.elapsed(now)),
+        };
+        let timer_display = format_duration(timer.remaining(now));
+
+        if area.width == 0 || area.height == 0 {
+            // Nothing fits until the window is given some room again.
+            if event::poll(Duration::from_millis(50))? && let Event::Resize(w, h) = event::read()? {
+                terminal.resize(Rect::new(0, 0, w, h))?;
+            }
+            continue;
+        }
+
+        if header_page_index != quote_page {
+            quote_page = header_page_index;
+            quote = next_quote(quote, quotes.len(), quote_seed());
+        }
+
+        let focus_locked = config.strict_focus
+            && (stopwatch.running || timer.running)
+            && note_input.is_none()
+            && !quit_prompt;
+
+        terminal.draw(|f| {
+
+            let area = f.area();
+            if area.width < MIN_WIDTH || area.height < MIN_HEIGHT {
+                let text = format!("Terminal too small\n(need {}×{})", MIN_WIDTH, MIN_HEIGHT);
+                let height = 2.min(area.height);
+                f.render_widget(
+                    Paragraph::new(text)
+                        .alignment(Alignment::Center)
+                        .wrap(Wrap { trim: true })
+                        .style(Style::default().fg(palette.alert)),
+                    Rect { y: area.y + (area.height - height) / 2, height, ..area },
+                );
+                return;
+            }
+
+            if focus_locked {
+                let clock = if timer.running { &timer_display } else { &stopwatch_display };
+                let clock = match bigdigits::render(clock) {
+                    Some(big) if config.big_clock && bigdigits::width(clock).is_some_and(|w| w <= f.area().width as usize) => big,
+                    _ => clock.clone(),
+                };
+                let text = format!(
+                    "Focusing…\n\n{}\n\n[{}] Pause/Stop  [{}] Quit",
+                    clock,
+                    keymap.label(Action::Toggle),
+                    keymap.label(Action::Quit),
+                );
+                let height = (text.lines().count() as u16).min(f.area().height);
+                let area = Rect {
+                    y: (f.area().height - height) / 2,
+                    height,
+                    ..f.area()
+                };
+                f.render_widget(
+                    Paragraph::new(text).alignment(Alignment::Center).style(Style::default().fg(palette.title)),
+                    area,
+                );
+                return;
+            }
+
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([
+                    Constraint::Length(2),      
+                    Constraint::Min(0),         
+                    Constraint::Length(2),      
+                ])
+                .split(f.area());
+
+            let header = Paragraph::new(format!("\n{}", header_pages[header_page_index]))
+                .alignment(Alignment::Center)
+                .style(Style::default().fg(palette.header));
+            let clock = config.show_clock.then(|| {
+                Local::now().format(if config.clock_format == "12h" { "%-I:%M %p" } else { "%H:%M" }).to_string()
+            });
+            let week_minutes = (week_seconds(store.as_ref(), Local::now().date_naive(), config.first_weekday())
+                + in_progress_seconds(&stopwatch, &timer, phase, now)) / 60;
+            let ring = week_ring(week_minutes, config.weekly_goal);
+            if clock.is_some() || ring.is_some() {
+                // Equal columns on both sides keep the page header centered.
+                let side = [&clock, &ring].iter()
+                    .filter_map(|s| s.as_ref().map(|s| s.chars().count() as u16 + 1))
+                    .max()
+                    .unwrap_or(0);
+                let header_chunks = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints([Constraint::Length(side), Constraint::Min(0), Constraint::Length(side)])
+                    .split(chunks[0]);
+                f.render_widget(header, header_chunks[1]);
+                if let Some(ring) = ring {
+                    let color = if week_minutes >= config.weekly_goal { palette.goal } else { palette.footer };
+                    f.render_widget(
+                        Paragraph::new(format!("\n {}", ring)).style(Style::default().fg(color)),
+                        header_chunks[0],
+                    );
+                }
+                if let Some(time) = clock {
+                    f.render_widget(
+                        Paragraph::new(format!("\n{} ", time)).alignment(Alignment::Right).style(Style::default().fg(palette.footer)),
+                        header_chunks[2],
+                    );
+                }
+            } else {
+                f.render_widget(header, chunks[0]);
+            }
+
+            let big_clock = config.big_clock
+                && header_page_index < 2
+                && duration_input.is_none()
+                && visible_height >= bigdigits::HEIGHT + 2
+                && bigdigits::width(if header_page_index == 0 { &stopwatch_display } else { &timer_display })
+                    .is_some_and(|w| w + 2 <= f.area().width as usize * 40 / 100);
+
+            let middle_chunks = if header_page_index == 2 {
+                Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([
+                        Constraint::Percentage(25), 
+                        Constraint::Min(visible_height as u16), 
+                        Constraint::Length(0),      
+                        Constraint::Length(1),      
+                        Constraint::Percentage(25), 
+                    ])
+                    .split(chunks[1])
+            } else {
+
+                let content_height: usize = if big_clock { bigdigits::HEIGHT + 2 } else { 3 };
+                let area_h = chunks[1].height as usize;
+
+                let remaining = area_h.saturating_sub(content_height + 2);
+                let top = (remaining / 2) as u16;
+                let bottom = (remaining - (remaining / 2)) as u16;
+
+                Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([
+                        Constraint::Length(top),                    
+                        Constraint::Length(content_height as u16),  
+                        Constraint::Length(1),                      
+                        Constraint::Length(1),                      
+                        Constraint::Length(bottom),                 
+                    ])
+                    .split(chunks[1])
+            };
+
+            let middle_inner = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([
+                    Constraint::Percentage(30), 
+                    Constraint::Percentage(40), 
+                    Constraint::Percentage(30), 
+                ])
+                .split(middle_chunks[1]);
+
+            let filtered_days = days_in_range(store.dated(), history_range);
+            let mut history_table: Option<HistoryTable> = None;
+            let middle_text = match header_page_index {
+                0 => stopwatch_display.clone(),
+                1 => {
+                    match &duration_input {
+                        Some(buf) if buf.contains(':') => format!("until {}_", buf),
+                        Some(buf) => format!("{}_ min", buf),
+                        None => timer_display.clone(),
+                    }
+                }
+                2 => {
+
+                    let mut parsed: Vec<(chrono::NaiveDate, String)> = Vec::new();
+                    let mut unparsable: Vec<String> = Vec::new();
+                    for (k, _secs) in store.entries() {
+                        match NaiveDate::parse_from_str(&k, "%Y-%m-%d") {
+                            Ok(d) => parsed.push((d, k)),
+                            Err(_) => unparsable.push(k),
+                        }
+                    }
+                    parsed.sort_by_key(|p| std::cmp::Reverse(p.0));
+
+                    let today = Local::now().date_naive();
+                    let seconds_of = |key: &String| store.total_for(key);
+                    let week_total = week_seconds(store.as_ref(), today, config.first_weekday()) / 60;
+                    let month_total: u64 = parsed.iter()
+                        .filter(|(d, _)| d.year() == today.year() && d.month() == today.month())
+                        .map(|(_d, key)| seconds_of(key))
+                        .sum::<u64>() / 60;
+
+                    // With a filter set, the summary rows total the filtered days.
+                    let summary = match history_range {
+                        Some(_) => {
+                            let (total, _days, average) = all_time_stats(&filtered_days);
+                            [("Total", total), ("Per active day", average)]
+                        }
+                        None => [("This week", week_total), ("This month", month_total)],
+                    };
+                    if let Some((from, to)) = history_range {
+                        parsed.retain(|(d, _)| *d >= from && *d <= to);
+                    } else {
+                        parsed.extend(unparsable.into_iter().map(|s| (NaiveDate::from_ymd_opt(1970,1,1).unwrap(), s)));
+                    }
+                    if !history_search.is_empty() {
+                        parsed.retain(|(_d, key)| key.starts_with(history_search.as_str()));
+                    }
+
+                    let (label, rows): (&str, Vec<(String, u64)>) = match history_view {
+                        HistoryView::Days => ("Date", parsed.iter()
+                            .map(|(_d, key)| (key.clone(), seconds_of(key) / 60))
+                            .collect()),
+                        HistoryView::Tags => ("Tag", tag_totals(store.as_ref(), parsed.iter().map(|(_d, key)| key.as_str()))
+                            .into_iter()
+                            .map(|(tag, secs)| (tag, secs / 60))
+                            .collect()),
+                        HistoryView::Chart | HistoryView::Heatmap | HistoryView::Hours => ("Date", Vec::new()),
+                    };
+
+                    // Inside the box's borders, below the summary rows.
+                    let inner_height = (middle_inner[1].height as usize).saturating_sub(2);
+                    let header_rows = 4; 
+                    let available_rows = inner_height.saturating_sub(header_rows);
+                    history_page_rows = available_rows.max(1);
+
+                    let total_rows = rows.len();
+                    history_selected = history_selected.min(total_rows.saturating_sub(1));
+                    if history_selected < history_offset {
+                        history_offset = history_selected;
+                    } else if history_selected >= history_offset + available_rows {
+                        history_offset = history_selected + 1 - available_rows;
+                    }
+                    history_offset = history_offset.min(total_rows.saturating_sub(available_rows));
+                    selected_day = match history_view {
+                        HistoryView::Days => rows.get(history_selected).map(|(key, _m)| key.clone()),
+                        _ => None,
+                    };
+
+                    let shown = |key: &str| match history_view {
+                        HistoryView::Days => display_date(key, &config.date_display_format),
+                        _ => key.to_string(),
+                    };
+                    let date_w = rows.iter()
+                        .map(|(key, _m)| shown(key).width())
+                        .chain(summary.iter().map(|(name, _m)| name.width()))
+                        .max()
+                        .unwrap_or(0)
+                        .max(11);
+                    let amount = |minutes: u64| {
+                        if config.human_readable_durations { human_minutes(minutes) } else { minutes.to_string() }
+                    };
+                    let amount_label = if config.human_readable_durations { "Time" } else { "Minutes" };
+                    let minutes_w = rows.iter()
+                        .map(|(_key, minutes)| amount(*minutes).width())
+                        .chain(summary.iter().map(|(_name, minutes)| amount(*minutes).width()))
+                        .max()
+                        .unwrap_or(0)
+                        .max(amount_label.width());
+
+                    let row = |name: &str, value: String| {
+                        Row::new(vec![pad_right(name, date_w) + " ", "|".to_string(), " ".to_string() + &pad_left(&value, minutes_w)])
+                    };
+                    let header = vec![
+                        row(summary[0].0, amount(summary[0].1)),
+                        row(summary[1].0, amount(summary[1].1)),
+                        row(label, amount_label.to_string()),
+                        Row::new(vec!["-".repeat(date_w + 1), "-".to_string(), "-".repeat(minutes_w + 1)]),
+                    ];
+                    let body: Vec<Row> = rows.iter()
+                        .map(|(key, minutes)| {
+                            let met_goal = history_view == HistoryView::Days
+                                && config.daily_goal > 0
+                                && *minutes >= config.daily_goal;
+                            let style = if met_goal { Style::default().fg(palette.goal) } else { Style::default() };
+                            row(&shown(key), amount(*minutes)).style(style)
+                        })
+                        .collect();
+
+                    if !matches!(history_view, HistoryView::Chart | HistoryView::Heatmap | HistoryView::Hours) {
+                        // Padding lives in the cells so the divider row stays unbroken.
+                        let widths = [
+                            Constraint::Length(date_w as u16 + 1),
+                            Constraint::Length(1),
+                            Constraint::Length(minutes_w as u16 + 1),
+                        ];
+                        history_table = Some(HistoryTable {
+                            header: Table::new(header, widths).column_spacing(0),
+                            body: Table::new(body, widths)
+                                .column_spacing(0)
+                                .highlight_style(Style::default().add_modifier(Modifier::REVERSED)),
+                            width: (date_w + 3 + minutes_w) as u16,
+                            total_rows,
+                            visible_rows: available_rows,
+                        });
+                    }
+                    String::new()
+                }
+                _ => "".to_string(),
+            };
+            let middle_text = if big_clock {
+                bigdigits::render(&middle_text).unwrap_or(middle_text)
+            } else {
+                middle_text
+            };
+
+            let warning_left = timer.remaining(now).as_secs();
+            let middle_style = if header_page_index == 1 && timer.done {
+                Style::default().fg(palette.alert)
+            } else if header_page_index == 1
+                && timer.running
+                && !timer.is_paused()
+                && warning_left < config.final_warning_seconds
+            {
+                let color = if warning_left * 2 < config.final_warning_seconds { palette.alert } else { palette.accent };
+                let style = Style::default().fg(color);
+                if config.final_warning_blink { style.add_modifier(Modifier::SLOW_BLINK) } else { style }
+            } else {
+                Style::default().fg(Color::default())
+            };
+
+            let middle = Paragraph::new(middle_text)
+                .alignment(Alignment::Center)
+                .block(
+                    Block::default()
+                    .borders(Borders::ALL) 
+                    .border_style(Style::default().fg(palette.border)) 
+                    .title(match header_page_index {
+                        0 | 1 => {
+                            let mut title = if header_page_index == 0 && break_started.is_some() {
+                                " Break".to_string()
+                            } else if header_page_index == 0 && idle_paused && !stopwatch.running {
+                                " Stopwatch · paused (idle)".to_string()
+                            } else if header_page_index == 0 {
+                                " Stopwatch".to_string()
+                            } else if config.pomodoro_enabled {
+                                format!(" Timer · {} #{}", phase.label(), cycle)
+                            } else {
+                                " Timer".to_string()
+                            };
+                            if header_page_index == 1 && timer.is_paused() {
+                                title.push_str(" · paused");
+                            }
+                            // Only while the timer still matches it, not after j/k or [i].
+                            if header_page_index == 1
+                                && let Some(preset) = active_preset.map(|i| &config.presets[i])
+                                && preset.minutes * 60 == timer.total.as_secs()
+                            {
+                                title.push_str(&format!(" · {}", preset.name));
+                            }
+                            if header_page_index == 1
+                                && !timer.is_idle()
+                                && let Some((target, total)) = timer_target
+                                && total == timer.total
+                            {
+                                let fmt = if config.clock_format == "12h" { "%-I:%M %p" } else { "%H:%M" };
+                                title.push_str(&format!(" · until {}", target.format(fmt)));
+                            }
+                            if let Some(tag) = tag_name(active_tag) {
+                                title.push_str(&format!(" · {}", tag));
+                            }
+                            let counting = if header_page_index == 0 {
+                                stopwatch.running
+                            } else {
+                                timer.running && !timer.is_paused()
+                            };
+                            if config.animations && counting {
+                                // Paced by the clock, not by how often frames are drawn.
+                                let frame = now.saturating_duration_since(started).as_millis() / SPINNER_FRAME.as_millis();
+                                title.push_str(&format!(" {}", SPINNER[frame as usize % SPINNER.len()]));
+                            }
+                            title.push(' ');
+                            title
+                        }
+                        2 => {
+                            let mut title = " History".to_string();
+                            if let Some((from, to)) = history_range {
+                                title.push_str(&format!(" ({})", format_range(from, to)));
+                            }
+                            if !history_search.is_empty() {
+                                title.push_str(&format!(" /{}", history_search));
+                            }
+                            if let Some(view) = history_view.label() {
+                                title.push_str(&format!(" · {}", view));
+                            }
+                            title.push(' ');
+                            title
+                        }
+                        _ => "".to_string(),
+                    })
+                    .title_bottom(match record_day(filtered_days.iter().copied()) {
+                        Some((day, secs)) if header_page_index == 2 => Line::from(format!(
+                            " Record: {} min on {} ",
+                            secs / 60,
+                            display_date(&day.format("%Y-%m-%d").to_string(), &config.date_display_format),
+                        )),
+                        _ if header_page_index == 1 && config.show_session_elapsed && !timer.is_idle() && !timer.done => {
+                            Line::styled(
+                                format!(" {} elapsed ", format_duration(timer.elapsed(now))),
+                                Style::default().fg(palette.footer),
+                            )
+                            .alignment(Alignment::Center)
+                        }
+                        _ => Line::default(),
+                    })
+                    .title_top(if header_page_index == 1 && config.show_finish_time && timer.running && !timer.is_paused() {
+                        // Worked out from what is left on every draw, so it
+                        // stays right after the machine was suspended.
+                        let fmt = if config.clock_format == "12h" { "%-I:%M %p" } else { "%H:%M" };
+                        let ends = Local::now() + chrono::TimeDelta::from_std(timer.remaining(now)).unwrap_or_default();
+                        Line::styled(format!(" ends at {} ", ends.format(fmt)), Style::default().fg(palette.footer))
+                            .alignment(Alignment::Right)
+                    } else {
+                        Line::default()
+                    })
+                    .title_bottom(match pomodoro_count(pomodoros.on(Local::now().date_naive()), config.daily_pomodoro_goal) {
+                        Some(text) if header_page_index == 1 => {
+                            let met = config.daily_pomodoro_goal > 0 && pomodoros.on(Local::now().date_naive()) >= config.daily_pomodoro_goal;
+                            let color = if met { palette.goal } else { palette.title };
+                            Line::styled(format!(" {} ", text), Style::default().fg(color)).alignment(Alignment::Right)
+                        }
+                        _ => Line::default(),
+                    })
+                    .title_style(Style::default().fg(palette.title)) 
+                    .title_alignment(Alignment::Left),
+                )
+                .style(middle_style);
+            f.render_widget(middle, middle_inner[1]);
+
+            if let Some(table) = history_table {
+                let inner = Block::default().borders(Borders::ALL).inner(middle_inner[1]);
+                let width = table.width.min(inner.width);
+                let area = Rect {
+                    x: inner.x + (inner.width - width) / 2,
+                    width,
+                    ..inner
+                };
+                let table_chunks = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Length(4), Constraint::Min(0)])
+                    .split(area);
+                f.render_widget(table.header, table_chunks[0]);
+                let mut state = TableState::default().with_offset(history_offset).with_selected(Some(history_selected));
+                f.render_stateful_widget(table.body, table_chunks[1], &mut state);
+
+                if table.total_rows > table.visible_rows {
+                    let mut scrollbar = ScrollbarState::new(table.total_rows - table.visible_rows)
+                        .position(history_offset)
+                        .viewport_content_length(table.visible_rows);
+                    f.render_stateful_widget(
+                        Scrollbar::new(ScrollbarOrientation::VerticalRight)
+                            .style(Style::default().fg(palette.border)),
+                        middle_inner[1].inner(Margin { vertical: 1, horizontal: 0 }),
+                        &mut scrollbar,
+                    );
+                }
+            }
+
+            if header_page_index == 2 && history_view == HistoryView::Chart {
+                let week = daily_series(store.as_ref(), Local::now().date_naive(), 7);
+                let labels: Vec<String> = week.iter().map(|(d, _m)| d.format("%a").to_string()).collect();
+                let data: Vec<(&str, u64)> = labels.iter().map(String::as_str).zip(week.iter().map(|(_d, m)| *m)).collect();
+
+                let inner = Block::default().borders(Borders::ALL).inner(middle_inner[1]);
+                let bar_gap: u16 = 1;
+                let bar_width = (inner.width.saturating_sub(bar_gap * 6) / 7).max(1);
+                let chart = BarChart::default()
+                    .data(&data)
+                    .bar_width(bar_width)
+                    .bar_gap(bar_gap)
+                    .bar_style(Style::default().fg(palette.accent))
+                    .label_style(Style::default().fg(palette.footer));
+                f.render_widget(chart, inner);
+            }
+
+            if header_page_index == 2 && history_view == HistoryView::Hours {
+                let inner = Block::default().borders(Borders::ALL).inner(middle_inner[1]);
+                let width = 24.min(inner.width);
+                let area = Rect {
+                    x: inner.x + (inner.width - width) / 2,
+                    width,
+                    ..inner
+                };
+                let rows = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Min(1), Constraint::Length(1)])
+                    .split(area);
+                let sparkline = Sparkline::default()
+                    .data(&hour_minutes)
+                    .style(Style::default().fg(palette.accent));
+                f.render_widget(sparkline, rows[0]);
+                f.render_widget(
+                    Paragraph::new("0     6     12    18   ").style(Style::default().fg(palette.footer)),
+                    rows[1],
+                );
+            }
+
+            if header_page_index == 2 && history_view == HistoryView::Heatmap {
+                let today = Local::now().date_naive();
+                let first = today.with_day(1).unwrap_or(today);
+                let minutes_on = |d: NaiveDate| store.total_for(&d.format("%Y-%m-%d").to_string()) / 60;
+                let weeks = month_grid(first, config.first_weekday());
+                let thresholds = quartiles(weeks.iter().flatten().flatten().map(|d| minutes_on(*d)).collect());
+
+                let day_names = if config.first_weekday() == Weekday::Sun { "Su Mo Tu We Th Fr Sa" } else { "Mo Tu We Th Fr Sa Su" };
+                let mut lines = vec![Line::styled(day_names, Style::default().fg(palette.footer))];
+                for week in &weeks {
+                    let mut spans: Vec<Span> = Vec::new();
+                    for (i, day) in week.iter().enumerate() {
+                        if i > 0 {
+                            spans.push(Span::raw(" "));
+                        }
+                        spans.push(match day {
+                            Some(d) => Span::styled(
+                                format!("{:>2}", d.day()),
+                                Style::default().fg(palette.heatmap[heat_level(minutes_on(*d), &thresholds)]),
+                            ),
+                            None => Span::raw("  "),
+                        });
+                    }
+                    lines.push(Line::from(spans));
+                }
+
+                let inner = Block::default().borders(Borders::ALL).inner(middle_inner[1]);
+                f.render_widget(Paragraph::new(lines).alignment(Alignment::Center), inner);
+            }
+
+            if header_page_index == 1 && !timer.is_idle() && !timer.total.is_zero() {
+                let elapsed = timer.elapsed(now).as_secs_f64() / timer.total.as_secs_f64();
+                let ratio = match config.gauge_direction.as_str() {
+                    "drain" => 1.0 - elapsed,
+                    _ => elapsed,
+                };
+                let gauge_area = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints([
+                        Constraint::Percentage(30), 
+                        Constraint::Percentage(40), 
+                        Constraint::Percentage(30), 
+                    ])
+                    .split(middle_chunks[2]);
+                let gauge = Gauge::default()
+                    .gauge_style(Style::default().fg(palette.accent))
+                    .ratio(ratio.clamp(0.0, 1.0));
+                f.render_widget(gauge, gauge_area[1]);
+            }
+
+            let today = Local::now().format("%Y-%m-%d").to_string();
+            let in_progress = in_progress_seconds(&stopwatch, &timer, phase, now);
+            let minutes_today = (store.total_for(&today) + in_progress) / 60;
+            let focused_text = if config.rich_summary {
+                let streak = current_streak(store.as_ref(), Local::now().date_naive());
+                fit_summary(
+                    &summary_parts(minutes_today, sessions_today, config.daily_goal, streak),
+                    middle_chunks[3].width as usize,
+                )
+            } else {
+                match goal_progress(minutes_today, config.daily_goal) {
+                    Some((text, _met)) => text,
+                    None if config.human_readable_durations => format!("{} focused today", human_minutes(minutes_today)),
+                    None => format!("{} minutes focused today", minutes_today),
+                }
+            };
+            let goal_met = config.daily_goal > 0 && minutes_today >= config.daily_goal;
+            if goal_met && !goal_notified {
+                goal_notified = true;
+                if config.notifications_enabled && !quiet {
+                    notify("Daily goal reached", &format!("{} minutes focused today", minutes_today));
+                }
+            }
+            if !record_notified
+                && let Some((day, secs)) = record_to_beat
+                && store.total_for(&today) + in_progress > secs
+            {
+                record_notified = true;
+                if config.celebrate_record && config.notifications_enabled && !quiet {
+                    notify(
+                        "New record!",
+                        &format!("{} minutes today, beating {} minutes on {}", minutes_today, secs / 60, day.format("%Y-%m-%d")),
+                    );
+                }
+            }
+            let focused_minutes_text = Paragraph::new(focused_text) 
+                .alignment(Alignment::Center)
+                .style(Style::default().fg(if goal_met { palette.goal } else { palette.accent }));
+            let session_running = (header_page_index == 0 && stopwatch.running) || (header_page_index == 1 && timer.running);
+            if let Some((buf, _session)) = &note_input {
+                let prompt = Paragraph::new(format!("Note: {}_  (Enter to save, Esc to skip)", buf))
+                    .alignment(Alignment::Center)
+                    .style(Style::default().fg(palette.accent));
+                f.render_widget(prompt, middle_chunks[3]);
+            } else if header_page_index != 2 && (config.always_show_today || !session_running) {
+                f.render_widget(focused_minutes_text, middle_chunks[3]);
+            }
+
+            // One blank row under the today line when there's room for it.
+            let trend_row = middle_chunks[4];
+            let quote_y = trend_row.y + if config.show_trend { 3 } else { 1 };
+            if config.show_quotes && header_page_index != 2 && !session_running && quote_y < trend_row.bottom() {
+                let area = Rect { y: quote_y, height: 1, ..trend_row };
+                let line = Paragraph::new(quotes[quote])
+                    .alignment(Alignment::Center)
+                    .style(Style::default().fg(palette.footer).add_modifier(Modifier::ITALIC));
+                f.render_widget(line, area);
+            }
+            if config.show_trend && header_page_index != 2 && trend_row.height > 0 {
+                let days = 14;
+                let series: Vec<u64> = daily_series(store.as_ref(), Local::now().date_naive(), days)
+                    .into_iter()
+                    .map(|(_d, minutes)| minutes)
+                    .collect();
+                let width = (days as u16).min(trend_row.width);
+                let area = Rect {
+                    x: trend_row.x + (trend_row.width - width) / 2,
+                    y: trend_row.y + u16::from(trend_row.height > 1),
+                    width,
+                    height: 1,
+                };
+                f.render_widget(Sparkline::default().data(&series).style(Style::default().fg(palette.accent)), area);
+            }
+
+            if header_page_index == 2 && note_input.is_none() {
+                let range_text = match (&range_input, history_range) {
+                    _ if move_input.is_some() => {
+                        let (from, buf) = move_input.as_ref().unwrap();
+                        format!("Move {} to (YYYY-MM-DD): {}_", from, buf)
+                    }
+                    _ if search_input => format!("Search: {}_", history_search),
+                    (Some(buf), _) => format!("Filter (YYYY, YYYY-MM or YYYY-MM-DD..YYYY-MM-DD): {}_", buf),
+                    (None, Some((from, to))) => {
+                        let dates: Vec<NaiveDate> = filtered_days.iter().filter(|(_d, secs)| *secs > 0).map(|(d, _secs)| *d).collect();
+                        let (_current, longest) = streaks(&dates, Local::now().date_naive());
+                        format!("{}: {} active days · longest streak {} days", format_range(from, to), dates.len(), longest)
+                    }
+                    (None, None) => {
+                        let (current, longest) = streaks(&logged_dates(store.as_ref()), Local::now().date_naive());
+                        let (total, _days, average) = all_time_stats(&store.dated());
+                        format!(
+                            "{}-day streak · longest {} days · {} min total, {} per active day",
+                            current, longest, total, average,
+                        )
+                    }
+                };
+                let range_line = Paragraph::new(range_text)
+                    .alignment(Alignment::Center)
+                    .style(Style::default().fg(palette.accent));
+                f.render_widget(range_line, middle_chunks[3]);
+            }
+
+            let footer_text = match &status_message {
+                Some((msg, at)) if at.elapsed() < STATUS_MESSAGE_DURATION => msg.clone(),
+                _ => format!(
+                    "[{}] Start/Reset [{}] Quit [{}]/[{}] Change Page [{}]/[{}] Adjust/Scroll",
+                    keymap.label(Action::Toggle),
+                    keymap.label(Action::Quit),
+                    keymap.label(Action::PrevPage),
+                    keymap.label(Action::NextPage),
+                    keymap.label(Action::Decrease),
+                    keymap.label(Action::Increase),
+                ),
+            };
+            let footer_text = match &persist_note {
+                Some(note) => format!("{}\n{}", footer_text, note),
+                None => footer_text,
+            };
+            let footer = Paragraph::new(footer_text)
+                .alignment(Alignment::Center)
+                .style(Style::default().fg(palette.footer));
+            f.render_widget(footer, chunks[2]);
+
+            let prompt = if quit_prompt {
+                Some(if config.quit_behavior == "confirm" {
+                    "Save the current session before quitting? [y]es [n]o [esc] cancel".to_string()
+                } else if !log_on_quit || (!stopwatch.running && phase != Phase::Work) {
+                    "Quit and discard the current session? [y/n]".to_string()
+                } else {
+                    "Quit and save the current session? [y/n]".to_string()
+                })
+            } else {
+                delete_prompt.as_ref().map(|day| {
+                    let minutes = store.total_for(day) / 60;
+                    format!(
+                        "Delete {} ({} minutes) from the history? [y/n]",
+                        display_date(day, &config.date_display_format),
+                        minutes,
+                    )
+                })
+            };
+            if show_help {
+                let entries: Vec<(String, &str)> = Action::ALL.iter()
+                    .map(|a| (keymap.labels(*a), a.description()))
+                    .collect();
+                let key_w = entries.iter().map(|(k, _)| k.chars().count()).max().unwrap_or(0);
+                let lines: Vec<String> = entries.iter()
+                    .map(|(k, d)| format!("{:<key_w$}  {}", k, d))
+                    .collect();
+                // Two columns when one doesn't fit the terminal's height.
+                let area = f.area();
+                let rows = if lines.len() as u16 + 2 > area.height { lines.len().div_ceil(2) } else { lines.len() };
+                let col_w = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+                let text: Vec<String> = (0..rows)
+                    .map(|i| match lines.get(i + rows) {
+                        Some(right) => format!("{:<col_w$}   {}", lines[i], right),
+                        None => lines[i].clone(),
+                    })
+                    .collect();
+                let width = (text.iter().map(|l| l.chars().count()).max().unwrap_or(0) as u16 + 4).min(area.width);
+                let height = (rows as u16 + 2).min(area.height);
+                let popup = Rect {
+                    x: area.x + (area.width - width) / 2,
+                    y: area.y + (area.height - height) / 2,
+                    width,
+                    height,
+                };
+                f.render_widget(Clear, popup);
+                f.render_widget(
+                    Paragraph::new(text.join("\n")).block(
+                        Block::default()
+                            .borders(Borders::ALL)
+                            .border_style(Style::default().fg(palette.border))
+                            .title(" Keys "),
+                    ),
+                    popup,
+                );
+            }
+
+            if let Some(text) = prompt {
+                let area = f.area();
+                let width = (text.chars().count() as u16 + 4).min(area.width);
+                let popup = Rect {
+                    x: area.x + (area.width - width) / 2,
+                    y: area.y + area.height.saturating_sub(3) / 2,
+                    width,
+                    height: 3.min(area.height),
+                };
+                f.render_widget(Clear, popup);
+                f.render_widget(
+                    Paragraph::new(text)
+                        .alignment(Alignment::Center)
+                        .block(Block::default().borders(Borders::ALL).border_style(Style::default().fg(palette.alert))),
+                    popup,
+                );
+            }
+        })?;
+
+        if config.status_file && status_written.is_none_or(|at| at.elapsed() >= STATUS_FILE_INTERVAL) {
+            let (mode, seconds) = if !stopwatch.is_idle() {
+                ("stopwatch", stopwatch.elapsed(now).as_secs())
+            } else if !timer.is_idle() {
+                ("timer", timer.remaining(now).as_secs())
+            } else {
+                ("idle", 0)
+            };
+            let today = Local::now().format("%Y-%m-%d").to_string();
+            let today_secs = store.total_for(&today) + in_progress_seconds(&stopwatch, &timer, phase, now);
+            let status = serde_json::json!({
+                "mode": mode,
+                "running": stopwatch.running || timer.running,
+                "seconds": seconds,
+                "phase": (config.pomodoro_enabled && mode == "timer").then(|| phase.label()),
+                "tag": tag_name(active_tag),
+                "today_minutes": today_secs / 60,
+            });
+            if let Err(e) = write_status(&status) {
+                status_message = Some((format!("Failed to write status file: {}", e), Instant::now()));
+            }
+            status_written = Some(Instant::now());
+        }
+
+        if timer.running && phase == Phase::Work && checkpoint_saved.elapsed() >= CHECKPOINT_INTERVAL {
+            let _ = save_checkpoint(timer.total, timer.remaining(Instant::now()));
+            checkpoint_saved = Instant::now();
+        }
+
+        // Only a running clock needs centisecond redraws.
+        let poll = if stopwatch.running || timer.running || break_started.is_some() {
+            Duration::from_millis(config.refresh_ms)
+        } else {
+            IDLE_POLL
+        };
+        if event::poll(poll)? {
+            let event = event::read()?;
+            last_input = last_activity(last_input, &event, Instant::now());
+            let key = match event {
+                Event::Key(key) => key,
+                Event::Resize(w, h) => {
+                    // Clear both buffers and redraw right away so the old layout
+                    // isn't diffed against the new size; history_offset is
+                    // re-clamped against the new row count while drawing.
+                    terminal.resize(Rect::new(0, 0, w, h))?;
+                    continue;
+                }
+                _ => continue,
+            };
+            if show_help {
+                if key.code == KeyCode::Esc || keymap.action(key.code) == Some(Action::Help) {
+                    show_help = false;
+                }
+                continue;
+            }
+
+            if quit_prompt {
+                let asks = config.quit_behavior == "confirm";
+                match key.code {
+                    KeyCode::Char('y') | KeyCode::Char('Y') => {
+                        // Otherwise [y] only agrees to quit; what happens to
+                        // the session stays as configured.
+                        if asks {
+                            log_on_quit = true;
+                        }
+                        break;
+                    }
+                    KeyCode::Char('n') | KeyCode::Char('N') if asks => {
+                        log_on_quit = false;
+                        break;
+                    }
+                    KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => quit_prompt = false,
+                    _ => {}
+                }
+                continue;
+            }
+
+            if let Some(day) = delete_prompt.take() {
+                if matches!(key.code, KeyCode::Char('y') | KeyCode::Char('Y')) {
+                    store.delete(&day);
+                    if let Err(e) = store.flush() {
+                        status_message = Some((format!("Failed to save history: {}", e), Instant::now()));
+                    } else {
+                        status_message = Some((
+                            format!("Deleted {}", display_date(&day, &config.date_display_format)),
+                            Instant::now(),
+                        ));
+                    }
+                }
+                continue;
+            }
+
+            if let Some((from, buf)) = move_input.as_mut() {
+                match key.code {
+                    KeyCode::Char(c) if c.is_ascii_digit() || c == '-' => buf.push(c),
+                    KeyCode::Backspace => {
+                        buf.pop();
+                    }
+                    KeyCode::Enter => {
+                        let msg = match NaiveDate::parse_from_str(buf, "%Y-%m-%d") {
+                            Ok(to) => {
+                                let to = to.format("%Y-%m-%d").to_string();
+                                let merged = store.move_day(from, &to);
+                                match store.flush() {
+                                    Err(e) => format!("Failed to save history: {}", e),
+                                    Ok(()) if merged => format!("Merged {} into {}", from, to),
+                                    Ok(()) => format!("Moved {} to {}", from, to),
+                                }
+                            }
+                            Err(_) => format!("Invalid date: {}", buf),
+                        };
+                        status_message = Some((msg, Instant::now()));
+                        move_input = None;
+                    }
+                    KeyCode::Esc => move_input = None,
+                    _ => {}
+                }
+                continue;
+            }
+
+            if let Some((buf, _session)) = note_input.as_mut() {
+                match key.code {
+                    KeyCode::Char(c) => buf.push(c),
+                    KeyCode::Backspace => {
+                        buf.pop();
+                    }
+                    KeyCode::Enter | KeyCode::Esc => {
+                        if let Some((buf, mut session)) = note_input.take() {
+                            if key.code == KeyCode::Enter && !buf.trim().is_empty() {
+                                session.note = Some(buf.trim().to_string());
+                            }
+                            save_session(&session);
+                        }
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+
+            if search_input {
+                match key.code {
+                    KeyCode::Char(c) if c.is_ascii_digit() || c == '-' => history_search.push(c),
+                    KeyCode::Backspace => {
+                        history_search.pop();
+                    }
+                    KeyCode::Enter => search_input = false,
+                    KeyCode::Esc => {
+                        history_search.clear();
+                        search_input = false;
+                    }
+                    _ => {}
+                }
+                history_offset = 0;
+                history_selected = 0;
+                continue;
+            }
+
+            if let Some(buf) = range_input.as_mut() {
+                match key.code {
+                    KeyCode::Char(c) if c.is_ascii_digit() || c == '-' || c == '.' => buf.push(c),
+                    KeyCode::Backspace => {
+                        buf.pop();
+                    }
+                    KeyCode::Enter => {
+                        if buf.is_empty() {
+                            history_range = None;
+                        } else if let Some(range) = parse_date_range(buf) {
+                            history_range = Some(range);
+                        } else {
+                            status_message = Some((format!("Invalid date range: {}", buf), Instant::now()));
+                        }
+                        history_offset = 0;
+                        history_selected = 0;
+                        range_input = None;
+                    }
+                    KeyCode::Esc => range_input = None,
+                    _ => {}
+                }
+                continue;
+            }
+
+            if let Some(buf) = duration_input.as_mut() {
+                match key.code {
+                    KeyCode::Char(c) if c.is_ascii_digit() && buf.len() < if buf.contains(':') { 5 } else { 3 } => {
+                        buf.push(c)
+                    }
+                    KeyCode::Char(':') if !buf.contains(':') && !buf.is_empty() => buf.push(':'),
+                    KeyCode::Backspace => {
+                        buf.pop();
+                    }
+                    KeyCode::Enter if buf.contains(':') => {
+                        match NaiveTime::parse_from_str(buf, "%H:%M") {
+                            Ok(target) => {
+                                let secs = seconds_until(Local::now().naive_local(), target);
+                                if secs < 60 {
+                                    status_message =
+                                        Some(("That time is less than a minute away".to_string(), Instant::now()));
+                                } else {
+                                    let total = Duration::from_secs(secs.min(CONFIG_TIMER_MAX * 60));
+                                    timer.set_total(total);
+                                    timer_target = Some((target, total));
+                                }
+                            }
+                            Err(_) => {
+                                status_message =
+                                    Some((format!("Not a valid time: {} (use HH:MM)", buf), Instant::now()));
+                            }
+                        }
+                        duration_input = None;
+                    }
+                    KeyCode::Enter => {
+                        match buf.parse::<u64>() {
+                            Ok(m) if (CONFIG_TIMER_MIN..=CONFIG_TIMER_MAX).contains(&m) => {
+                                timer.set_total(Duration::from_secs(m * 60));
+                            }
+                            _ if buf.is_empty() => {}
+                            _ => {
+                                status_message = Some((
+                                    format!("Timer must be between {} and {} minutes", CONFIG_TIMER_MIN, CONFIG_TIMER_MAX),
+                                    Instant::now(),
+                                ));
+                            }
+                        }
+                        duration_input = None;
+                    }
+                    KeyCode::Esc => duration_input = None,
+                    _ => {}
+                }
+                continue;
+            }
+
+            // The first key of a chord such as `gg` waits for the next one;
+            // anything else pressed in between drops it.
+            let chord = pending_key.take().and_then(|first| keymap.chord(first, key.code));
+            let action = if chord.is_some() {
+                chord
+            } else if header_page_index == 2 && keymap.starts_chord(key.code) {
+                pending_key = Some(key.code);
+                continue;
+            } else {
+                keymap.action(key.code)
+            };
+
+            if focus_locked && !matches!(action, Some(Action::Toggle | Action::Quit)) {
+                continue;
+            }
+
+            match action {
+                Some(Action::NextPage) if !timer.running && !stopwatch.running => {
+                    header_page_index = (header_page_index + 1) % header_pages.len();
+                }
+                Some(Action::PrevPage) if !timer.running && !stopwatch.running => {
+                    header_page_index = (header_page_index + header_pages.len() - 1) % header_pages.len();
+                }
+
+                Some(Action::Increase) => {
+                    match header_page_index {
+                        1 if timer.is_idle() => {
+                            timer.set_total((timer.total + extra).min(timer_max));
+                        },
+                        2 => { 
+                            history_selected = history_selected.saturating_sub(1);
+                        },
+                        _ => {}
+                    }
+                },
+                Some(Action::Decrease) => {
+                    match header_page_index {
+                        1 if timer.is_idle() => {
+                            timer.set_total((timer.total.saturating_sub(extra)).max(timer_min));
+                        },
+
+                        2 => { 
+                            history_selected = history_selected.saturating_add(1);
+                        },
+                        _ => {}
+                    }
+                },
+                Some(Action::IncreaseMore) if header_page_index == 1 && timer.is_idle() => {
+                    timer.set_total((timer.total + coarse).min(timer_max));
+                }
+                Some(Action::DecreaseMore) if header_page_index == 1 && timer.is_idle() => {
+                    timer.set_total((timer.total.saturating_sub(coarse)).max(timer_min));
+                }
+                Some(Action::Filter) if header_page_index == 2 => {
+                    range_input = Some(String::new());
+                }
+                Some(Action::PageUp) if header_page_index == 2 => {
+                    history_selected = history_selected.saturating_sub(history_page_rows);
+                }
+                Some(Action::PageDown) if header_page_index == 2 => {
+                    history_selected = history_selected.saturating_add(history_page_rows);
+                }
+                Some(Action::Top) if header_page_index == 2 => history_selected = 0,
+                // Clamped to the last page while drawing.
+                Some(Action::Bottom) if header_page_index == 2 => history_selected = usize::MAX,
+                Some(Action::Search) if header_page_index == 2 => {
+                    search_input = true;
+                }
+                Some(Action::ClearFilter) if header_page_index == 2 => {
+                    history_search.clear();
+                    history_range = None;
+                    history_offset = 0;
+                    history_selected = 0;
+                }
+                Some(Action::Preset) if header_page_index == 1 && timer.is_idle() => {
+                    if config.presets.is_empty() {
+                        status_message = Some(("No presets configured (see presets in the config file)".to_string(), Instant::now()));
+                    } else {
+                        active_preset = match active_preset {
+                            None => Some(0),
+                            Some(i) if i + 1 < config.presets.len() => Some(i + 1),
+                            Some(_) => None,
+                        };
+                        timer.set_total(match active_preset {
+                            Some(i) => Duration::from_secs(config.presets[i].minutes * 60),
+                            None => Duration::from_secs(config.default_timer_duration * 60),
+                        });
+                    }
+                }
+                Some(Action::Tag) if header_page_index < 2 && timer.is_idle() && stopwatch.is_idle() => {
+                    if config.tags.is_empty() {
+                        status_message = Some(("No tags configured (see tags in the config file)".to_string(), Instant::now()));
+                    } else {
+                        active_tag = match active_tag {
+                            None => Some(0),
+                            Some(i) if i + 1 < config.tags.len() => Some(i + 1),
+                            Some(_) => None,
+                        };
+                    }
+                }
+                Some(Action::Undo) if header_page_index == 2 => {
+                    let msg = match undo.pop() {
+                        Some(session) => {
+                            let removed = undo_session(store.as_mut(), &session);
+                            // A pomodoro still waiting for its note was never written.
+                            if note_input.as_ref().is_some_and(|(_buf, pending)| session.wrote(pending)) {
+                                note_input = None;
+                            } else {
+                                remove_session(&session);
+                            }
+                            if session.completed && let Ok(day) = NaiveDate::parse_from_str(&session.day, "%Y-%m-%d") {
+                                pomodoros.remove(day);
+                            }
+                            if history_view == HistoryView::Hours {
+                                hour_minutes = hour_histogram(&read_sessions());
+                            }
+                            if removed < 60 {
+                                format!("Removed {} seconds from {}", removed, session.day)
+                            } else {
+                                format!("Removed {} minutes from {}", removed / 60, session.day)
+                            }
+                        }
+                        None => "Nothing to undo".to_string(),
+                    };
+                    status_message = Some((msg, Instant::now()));
+                }
+                Some(Action::Delete) if header_page_index == 2 => match &selected_day {
+                    Some(day) => delete_prompt = Some(day.clone()),
+                    None if history_view == HistoryView::Days => {
+                        status_message = Some(("Nothing to delete".to_string(), Instant::now()));
+                    }
+                    None => {
+                        status_message = Some(("Switch to the list of days to delete one".to_string(), Instant::now()));
+                    }
+                },
+                Some(Action::Move) if header_page_index == 2 => match &selected_day {
+                    Some(day) => move_input = Some((day.clone(), String::new())),
+                    None => {
+                        status_message = Some(("Switch to the list of days to move one".to_string(), Instant::now()));
+                    }
+                },
+                Some(Action::Export) if header_page_index == 2 => {
+                    let msg = match export_csv_file(store.as_ref()) {
+                        Ok(path) => format!("Exported to {}", path.display()),
+                        Err(e) => format!("Export failed: {}", e),
+                    };
+                    status_message = Some((msg, Instant::now()));
+                }
+                Some(Action::View) if header_page_index == 2 => {
+                    history_view = history_view.next();
+                    history_offset = 0;
+                    history_selected = 0;
+                    if history_view == HistoryView::Hours {
+                        hour_minutes = hour_histogram(&read_sessions());
+                    }
+                }
+                Some(Action::FinishGoal) if header_page_index == 1 && timer.is_idle() => {
+                    let today = Local::now().format("%Y-%m-%d").to_string();
+                    let in_progress = in_progress_seconds(&stopwatch, &timer, phase, now);
+                    let minutes_today = (store.total_for(&today) + in_progress) / 60;
+                    if config.daily_goal == 0 {
+                        status_message = Some(("No daily_goal is set".to_string(), Instant::now()));
+                    } else if minutes_today >= config.daily_goal {
+                        status_message = Some(("Daily goal already reached".to_string(), Instant::now()));
+                    } else {
+                        let minutes = (config.daily_goal - minutes_today).clamp(CONFIG_TIMER_MIN, CONFIG_TIMER_MAX);
+                        timer.set_total(Duration::from_secs(minutes * 60));
+                    }
+                }
+                Some(Action::Input) if header_page_index == 1 && timer.is_idle() => {
+                    duration_input = Some(String::new());
+                }
+                Some(Action::Rearm) if header_page_index == 1 && timer.is_idle() => {
+                    timer.set_total(match config.rearm_target.as_str() {
+                        "last" => timer.last_started_total,
+                        _ => Duration::from_secs(config.default_timer_duration * 60),
+                    });
+                }
+                Some(Action::Toggle) => match header_page_index {
+                    0 if break_started.is_some() => {
+                        break_started = None;
+                        timer.done = false;
+                        header_page_index = 1;
+                    }
+                    0 => {
+                        stopwatch.toggle(Instant::now());
+                        idle_paused = false;
+                    }
+                    1 => {
+
+                        if timer.done {
+                            timer.done = false;
+                        } else if !timer.is_idle() && config.pomodoro_enabled {
+                            let secs = partial(&timer, phase);
+                            log_seconds(store.as_mut(), tag_name(active_tag), secs);
+                            let session = SessionRecord::new(tag_name(active_tag), timer.flushed + secs, None);
+                            push_undo(&mut undo, &session, secs);
+                            save_session(&session);
+                            (phase, cycle) = phase.next(cycle, config.cycles_before_long_break);
+                            timer.restart(phase.duration(&config), Instant::now());
+                            clear_checkpoint();
+                        } else if !timer.is_idle() {
+                            let secs = partial(&timer, phase);
+                            log_seconds(store.as_mut(), tag_name(active_tag), secs);
+                            let session = SessionRecord::new(tag_name(active_tag), timer.flushed + secs, None);
+                            push_undo(&mut undo, &session, secs);
+                            save_session(&session);
+                            timer.stop();
+                            clear_checkpoint();
+                        } else {
+                            break_started = None;
+                            timer.start(Instant::now());
+                            if phase == Phase::Work {
+                                let _ = save_checkpoint(timer.total, timer.total);
+                            }
+                            checkpoint_saved = Instant::now();
+                        }
+                    }
+                    _ => {}
+                },
+                Some(Action::Pause) if header_page_index == 1 && !timer.is_idle() => {
+                    timer.toggle_pause(Instant::now());
+                    if timer.running && phase == Phase::Work {
+                        let _ = save_checkpoint(timer.total, timer.remaining(Instant::now()));
+                        checkpoint_saved = Instant::now();
+                    } else {
+                        // A checkpoint ages while paused, so --resume would
+                        // pick the interval up with the pause counted as run.
+                        clear_checkpoint();
+                    }
+                }
+                Some(Action::Help) => show_help = true,
+                Some(Action::Copy) if config.clipboard_enabled => {
+                    if clipboard.is_none() {
+                        clipboard = arboard::Clipboard::new().ok();
+                    }
+                    let msg = match clipboard.as_mut().map(|c| c.set_text(today_summary(store.as_ref(), sessions_today))) {
+                        Some(Ok(())) => "Copied today's summary to the clipboard",
+                        _ => "Clipboard is not available",
+                    };
+                    status_message = Some((msg.to_string(), Instant::now()));
+                }
+                Some(Action::Reset) if header_page_index == 0 => {
+                    idle_paused = false;
+                    let autosaved = stopwatch.flushed;
+                    let secs = settle(autosaved, stopwatch.reset(Instant::now()));
+                    log_seconds(store.as_mut(), tag_name(active_tag), secs);
+                    let session = SessionRecord::new(tag_name(active_tag), autosaved + secs, None);
+                    push_undo(&mut undo, &session, autosaved + secs);
+                    save_session(&session);
+                }
+                Some(Action::Reset) if header_page_index == 1 && config.pomodoro_enabled => {
+                    let secs = partial(&timer, phase);
+                    log_seconds(store.as_mut(), tag_name(active_tag), secs);
+                    let session = SessionRecord::new(tag_name(active_tag), timer.flushed + secs, None);
+                    push_undo(&mut undo, &session, secs);
+                    save_session(&session);
+                    timer.stop();
+                    phase = Phase::Work;
+                    cycle = 1;
+                    timer.set_total(phase.duration(&config));
+                    clear_checkpoint();
+                }
+                Some(Action::Quit)
+                    if (config.confirm_quit_while_running || config.quit_behavior == "confirm")
+                        && (stopwatch.running || timer.running) =>
+                {
+                    quit_prompt = true;
+                }
+                Some(Action::Quit) => break,
+                _ => {}
+            }
+        }
+    }
+
+    // Whatever is on the clock when quitting is settled the same way a reset
+    // stopwatch is, then logged or dropped as `quit_behavior` says; break
+    // intervals are never logged.
+    let autosaved = stopwatch.flushed;
+    let (stopwatch_secs, timer_secs) = quit_seconds(&mut stopwatch, &timer, phase, Instant::now(), settle);
+    if log_on_quit {
+        log_seconds(store.as_mut(), tag_name(active_tag), stopwatch_secs + timer_secs);
+        save_session(&SessionRecord::new(tag_name(active_tag), autosaved + stopwatch_secs, None));
+        save_session(&SessionRecord::new(tag_name(active_tag), timer.flushed + timer_secs, None));
+    }
+    if let Some((_buf, session)) = note_input.take() {
+        save_session(&session);
+    }
+
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+
+    if let Err(e) = store.flush() {
+        eprintln!("Failed to save history: {}", e);
+    }
+
+    let remember_timer = config.remember_last_timer && !config.pomodoro_enabled;
+    if remember_timer || config.remember_last_page {
+        let mut state = load_state();
+        if remember_timer {
+            state.last_timer_secs = Some(timer.total.as_secs());
+        }
+        if config.remember_last_page {
+            state.last_page = Some(header_page_index);
+        }
+        if let Err(e) = save_state(&state) {
+            eprintln!("Failed to save state: {}", e);
+        }
+    }
+
+    clear_checkpoint();
+    if config.status_file {
+        clear_status();
+    }
+    release_lock(lock);
+    Ok(())
+}
+
+/// Clock text shared by the stopwatch and the timer: `MM:SS.cc` under an
+/// hour and `H:MM:SS` from then on.
+fn format_duration(dur: Duration) -> String {
+    let hours = dur.as_secs() / 3600;
+    let mins = dur.as_secs() / 60;
+    let secs = dur.as_secs() % 60;
+    let centis = dur.subsec_millis() / 10;
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, mins % 60, secs)
+    } else {
+        format!("{:02}:{:02}.{:02}", mins, secs, centis)
+    }
+}
+
+/// The first day of the week `d` falls in, for weeks starting on `first`.
+fn week_of(d: NaiveDate, first: Weekday) -> NaiveDate {
+    d - chrono::Days::new(d.weekday().days_since(first) as u64)
+}
+
+/// Seconds logged in the week containing `today`.
+fn week_seconds(store: &dyn HistoryStore, today: NaiveDate, first: Weekday) -> u64 {
+    let week = week_of(today, first);
+    store.dated().into_iter()
+        .filter(|(d, _secs)| week_of(*d, first) == week)
+        .map(|(_d, secs)| secs)
+        .sum()
+}
+
+/// Progress toward the weekly goal as a quarter-filled circle and hours,
+/// e.g. "◑ 5h30/10h", or `None` when no goal is set.
+fn week_ring(minutes: u64, weekly_goal: u64) -> Option<String> {
+    let quarters = (minutes * 4).checked_div(weekly_goal)?.min(4);
+    let ring = ['○', '◔', '◑', '◕', '●'][quarters as usize];
+    let hours = |m: u64| match m % 60 {
+        0 => format!("{}h", m / 60),
+        rest => format!("{}h{:02}", m / 60, rest),
+    };
+    Some(format!("{} {}/{}", ring, hours(minutes), hours(weekly_goal)))
+}
+
+fn notify(summary: &str, body: &str) {
+    let summary = summary.to_string();
+    let body = body.to_string();
+    std::thread::spawn(move || {
+        let _ = notify_rust::Notification::new()
+            .appname("fokus")
+            .summary(&summary)
+            .body(&body)
+            .show();
+    });
+}
+
+fn play_sound(command: Option<&str>) {
+    match command {
+        Some(cmd) => {
+            let child = std::process::Command::new("sh")
+                .arg("-c")
+                .arg(cmd)
+                .stdin(std::process::Stdio::null())
+                .stdout(std::process::Stdio::null())
+                .stderr(std::process::Stdio::null())
+                .spawn();
+            if let Ok(mut child) = child {
+                std::thread::spawn(move || child.wait());
+            }
+        }
+        None => {
+            use std::io::Write;
+            print!("\x07");
+            let _ = io::stdout().flush();
+        }
+    }
+}
+
+
+fn current_streak(store: &dyn HistoryStore, today: NaiveDate) -> u64 {
+    streaks(&logged_dates(store), today).0
+}
+
+/// Dates with any focus time logged, in ascending order.
+fn logged_dates(store: &dyn HistoryStore) -> Vec<NaiveDate> {
+    store.dated().into_iter()
+        .filter(|(_d, secs)| *secs > 0)
+        .map(|(d, _secs)| d)
+        .collect()
+}
+
+/// Returns the current and the longest run of consecutive days in `dates`,
+/// which must be sorted ascending. The current streak is still alive if it
+/// ended yesterday, since today may not have been logged yet.
+fn streaks(dates: &[NaiveDate], today: NaiveDate) -> (u64, u64) {
+    let mut longest = 0;
+    let mut run = 0;
+    let mut prev: Option<NaiveDate> = None;
+    for &d in dates {
+        run = match prev {
+            Some(p) if p == d => run,
+            Some(p) if p.succ_opt() == Some(d) => run + 1,
+            _ => 1,
+        };
+        longest = longest.max(run);
+        prev = Some(d);
+    }
+
+    let current = match prev {
+        Some(last) if last == today || last.succ_opt() == Some(today) => run,
+        _ => 0,
+    };
+    (current, longest)
+}
+
+/// The day with the most time logged and its seconds, preferring the most
+/// recent one on a tie. Only entries keyed by a valid date count.
+fn record_day(days: impl IntoIterator<Item = (NaiveDate, u64)>) -> Option<(NaiveDate, u64)> {
+    days.into_iter()
+        .filter(|(_d, secs)| *secs > 0)
+        .max_by_key(|(d, secs)| (*secs, *d))
+}
+
+/// The dated entries that fall inside the History page's filter, or all of
+/// them when there is none.
+fn days_in_range(mut days: Vec<(NaiveDate, u64)>, range: Option<(NaiveDate, NaiveDate)>) -> Vec<(NaiveDate, u64)> {
+    if let Some((from, to)) = range {
+        days.retain(|(d, _secs)| (from..=to).contains(d));
+    }
+    days
+}
+
+/// Total minutes ever logged, the number of days with any time logged, and
+/// the average minutes over those days. Like the streaks, this only counts
+/// entries keyed by a valid date, so stray keys in a hand-edited history
+/// don't skew it.
+fn all_time_stats(days: &[(NaiveDate, u64)]) -> (u64, u64, u64) {
+    let (secs, days) = days.iter()
+        .filter(|(_d, secs)| *secs > 0)
+        .fold((0, 0), |(total, days), (_d, secs)| (total + secs, days + 1));
+    let average = secs.checked_div(days).unwrap_or(0) / 60;
+    (secs / 60, days, average)
+}
+
+fn today_summary(store: &dyn HistoryStore, sessions: u64) -> String {
+    let now = Local::now();
+    let today = now.format("%Y-%m-%d").to_string();
+    let minutes_today = store.total_for(&today) / 60;
+    let streak = current_streak(store, now.date_naive());
+    format!("Focused {}m today across {}, {}-day streak", minutes_today, count_sessions(sessions), streak)
+}
+
+fn count_sessions(n: u64) -> String {
+    if n == 1 { "1 session".to_string() } else { format!("{} sessions", n) }
+}
+
+/// Rounds a session's seconds to whole minutes for `mode` ("floor", "round"
+/// or "ceil"), leaving them as they are for "exact".
+fn round_session(secs: u64, mode: &str) -> u64 {
+    let minutes = match mode {
+        "floor" => secs / 60,
+        "round" => (secs + 30) / 60,
+        "ceil" => secs.div_ceil(60),
+        _ => return secs,
+    };
+    minutes * 60
+}
+
+/// Today's completed pomodoros as "🍅 ×4 today", or "🍅 ×4/8 today" toward a
+/// goal. `None` before the first one when there is no goal to show.
+fn pomodoro_count(count: u64, goal: u64) -> Option<String> {
+    match (count, goal) {
+        (0, 0) => None,
+        (_, 0) => Some(format!("🍅 ×{} today", count)),
+        _ => Some(format!("🍅 ×{}/{} today", count, goal)),
+    }
+}
+
+/// "N minutes" under an hour, "Xh Ym" from there on.
+fn human_minutes(minutes: u64) -> String {
+    if minutes < 60 {
+        format!("{} minutes", minutes)
+    } else {
+        format!("{}h {}m", minutes / 60, minutes % 60)
+    }
+}
+
+/// Formats progress toward the daily goal as "45 / 120 minutes (37%)" along
+/// with whether it has been met, or `None` when no goal is set.
+fn goal_progress(minutes_today: u64, daily_goal: u64) -> Option<(String, bool)> {
+    let pct = (minutes_today * 100).checked_div(daily_goal)?;
+    Some((
+        format!("{} / {} minutes ({}%)", minutes_today, daily_goal, pct),
+        minutes_today >= daily_goal,
+    ))
+}
+
+fn summary_parts(minutes_today: u64, sessions: u64, daily_goal: u64, streak: u64) -> Vec<String> {
+    let mut parts = vec![format!("{}m today", minutes_today), count_sessions(sessions)];
+    if let Some(pct) = (minutes_today * 100).checked_div(daily_goal) {
+        parts.push(format!("{}% of goal", pct));
+    }
+    parts.push(format!("{}-day streak", streak));
+    parts
+}
+
+fn fit_summary(parts: &[String], width: usize) -> String {
+    for n in (1..=parts.len()).rev() {
+        let s = parts[..n].join(" · ");
+        if s.chars().count() <= width {
+            return s;
+        }
+    }
+    parts.first().map(|p| p.chars().take(width).collect()).unwrap_or_default()
+}
+
+fn tag_totals<'a>(store: &dyn HistoryStore, days: impl Iterator<Item = &'a str>) -> Vec<(String, u64)> {
+    let mut totals: HashMap<String, u64> = HashMap::new();
+    for day in days {
+        let day_tags = store.tags_on(day);
+        let tagged: u64 = day_tags.iter().map(|(_tag, secs)| secs).sum();
+        for (tag, secs) in day_tags {
+            *totals.entry(tag).or_insert(0) += secs;
+        }
+        let untagged = store.total_for(day).saturating_sub(tagged);
+        if untagged > 0 {
+            *totals.entry("(untagged)".to_string()).or_insert(0) += untagged;
+        }
+    }
+
+    let mut rows: Vec<(String, u64)> = totals.into_iter().collect();
+    rows.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    rows
+}
+
+/// Lays out the month starting at `first` as weeks starting on `week_start`,
+/// with `None` for the cells before the 1st and after the last day.
+fn month_grid(first: NaiveDate, week_start: Weekday) -> Vec<[Option<NaiveDate>; 7]> {
+    let mut weeks: Vec<[Option<NaiveDate>; 7]> = Vec::new();
+    let mut week = [None; 7];
+    let mut col = first.weekday().days_since(week_start) as usize;
+    for d in first.iter_days().take_while(|d| d.month() == first.month()) {
+        week[col] = Some(d);
+        col += 1;
+        if col == 7 {
+            weeks.push(week);
+            week = [None; 7];
+            col = 0;
+        }
+    }
+    if col > 0 {
+        weeks.push(week);
+    }
+    weeks
+}
+
+/// Quartile cut points of the days with any focus time.
+fn quartiles(mut minutes: Vec<u64>) -> [u64; 3] {
+    minutes.retain(|m| *m > 0);
+    minutes.sort_unstable();
+    if minutes.is_empty() {
+        return [0; 3];
+    }
+    let at = |q: usize| minutes[(minutes.len() - 1) * q / 4];
+    [at(1), at(2), at(3)]
+}
+
+/// 0 for days with nothing logged, otherwise 1-4 by quartile.
+fn heat_level(minutes: u64, thresholds: &[u64; 3]) -> usize {
+    if minutes == 0 {
+        0
+    } else {
+        1 + thresholds.iter().filter(|t| minutes > **t).count()
+    }
+}
+
+/// The day count given to --last, if it is one --export-svg can draw.
+fn parse_last(arg: Option<&str>) -> Option<u64> {
+    arg?.parse::<u64>().ok().filter(|n| (1..=SVG_MAX_DAYS).contains(n))
+}
+
+fn daily_series(store: &dyn HistoryStore, end: NaiveDate, days: u64) -> Vec<(NaiveDate, u64)> {
+    (0..days)
+        .rev()
+        .filter_map(|back| end.checked_sub_days(chrono::Days::new(back)))
+        .map(|d| (d, store.total_for(&d.format("%Y-%m-%d").to_string()) / 60))
+        .collect()
+}
+
+fn render_svg(series: &[(NaiveDate, u64)]) -> String {
+    let bar_w = 20;
+    let gap = 4;
+    let left = 50;
+    let top = 20;
+    let plot_h = 200;
+    let bottom = 60;
+    let width = left + series.len() * (bar_w + gap) + 20;
+    let height = top + plot_h + bottom;
+    let max = series.iter().map(|(_, m)| *m).max().unwrap_or(0).max(1);
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" font-family=\"monospace\" font-size=\"10\">\n\
+         <rect width=\"100%\" height=\"100%\" fill=\"white\"/>\n\
+         <line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"black\"/>\n\
+         <line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"black\"/>\n",
+        width, height,
+        left, top, left, top + plot_h,
+        left, top + plot_h, width - 10, top + plot_h
+    );
+
+    for tick in [0, max / 2, max] {
+        let y = top + plot_h - (tick as usize * plot_h) / max as usize;
+        svg.push_str(&format!(
+                "<text x=\"{}\" y=\"{}\" text-anchor=\"end\">{}</text>\n",
+                left - 6, y + 3, tick
+        ));
+    }
+    svg.push_str(&format!(
+            "<text x=\"12\" y=\"{}\" transform=\"rotate(-90 12 {})\" text-anchor=\"middle\">Minutes</text>\n",
+            top + plot_h / 2, top + plot_h / 2
+    ));
+
+    for (i, (date, minutes)) in series.iter().enumerate() {
+        let x = left + gap + i * (bar_w + gap);
+        let h = (*minutes as usize * plot_h) / max as usize;
+        svg.push_str(&format!(
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"teal\"><title>{}: {} minutes</title></rect>\n",
+                x, top + plot_h - h, bar_w, h, date.format("%Y-%m-%d"), minutes
+        ));
+        let lx = x + bar_w / 2;
+        let ly = top + plot_h + 12;
+        svg.push_str(&format!(
+                "<text x=\"{}\" y=\"{}\" transform=\"rotate(-45 {} {})\" text-anchor=\"end\">{}</text>\n",
+                lx, ly, lx, ly, date.format("%m-%d")
+        ));
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn export_svg(path: &Path, days: u64) -> io::Result<()> {
+    let store = JsonHistory::read()?;
+    let series = daily_series(&store, Local::now().date_naive(), days);
+    fs::write(path, render_svg(&series))?;
+    println!("Exported the last {} days to {}", days, path.display());
+    Ok(())
+}
+
+/// Renders history as `date,minutes` rows in ascending date order, returning
+/// the keys that are not dates alongside so callers can report them.
+fn render_csv(store: &dyn HistoryStore) -> (String, Vec<String>) {
+    let mut skipped: Vec<String> = store.entries().into_iter()
+        .map(|(k, _secs)| k)
+        .filter(|k| NaiveDate::parse_from_str(k, "%Y-%m-%d").is_err())
+        .collect();
+    skipped.sort();
+
+    let mut csv = String::from("date,minutes\n");
+    for (key, secs) in store.iter_sorted() {
+        csv.push_str(&format!("{},{}\n", key, secs / 60));
+    }
+    (csv, skipped)
+}
+
+fn print_today(store: &dyn HistoryStore, json: bool) -> io::Result<()> {
+    println!("{}", render_today(store, Local::now().date_naive(), json));
+    Ok(())
+}
+
+/// What --today prints for `day`: the minutes alone, or them and the date as
+/// JSON.
+fn render_today(store: &dyn HistoryStore, day: NaiveDate, json: bool) -> String {
+    let key = day.format("%Y-%m-%d").to_string();
+    let minutes = store.total_for(&key) / 60;
+    if json {
+        serde_json::json!({ "date": key, "minutes": minutes }).to_string()
+    } else {
+        minutes.to_string()
+    }
+}
+
+struct YearSummary {
+    minutes: u64,
+    active_days: u64,
+    average: u64,
+    best_day: Option<(NaiveDate, u64)>,
+    /// Minutes per month, January first.
+    months: [u64; 12],
+}
+
+fn year_summary(store: &dyn HistoryStore, year: i32) -> YearSummary {
+    let days: Vec<(NaiveDate, u64)> = store.dated().into_iter()
+        .filter(|(d, _secs)| d.year() == year)
+        .collect();
+    let (minutes, active_days, average) = all_time_stats(&days);
+
+    let mut months = [0u64; 12];
+    for (d, secs) in &days {
+        months[d.month0() as usize] += secs;
+    }
+    let best_day = days.iter()
+        .filter(|(_d, secs)| *secs > 0)
+        .max_by_key(|(d, secs)| (*secs, std::cmp::Reverse(*d)))
+        .map(|(d, secs)| (*d, secs / 60));
+    YearSummary { minutes, active_days, average, best_day, months: months.map(|s| s / 60) }
+}
+
+fn print_year_summary(store: &dyn HistoryStore, year: i32, json: bool) -> io::Result<()> {
+    let summary = year_summary(store, year);
+    if json {
+        println!("{}", serde_json::json!({
+            "year": year,
+            "minutes": summary.minutes,
+            "active_days": summary.active_days,
+            "average": summary.average,
+            "best_day": summary.best_day.map(|(d, m)| serde_json::json!({
+                "date": d.format("%Y-%m-%d").to_string(),
+                "minutes": m,
+            })),
+            "months": summary.months,
+        }));
+        return Ok(());
+    }
+
+    println!("{} in review", year);
+    println!("Total        {} minutes", summary.minutes);
+    println!("Active days  {} (average {} minutes)", summary.active_days, summary.average);
+    match summary.best_day {
+        Some((d, m)) => println!("Best day     {} ({} minutes)", d.format("%Y-%m-%d"), m),
+        None => println!("Best day     -"),
+    }
+    println!();
+    println!("Month  Minutes");
+    for (i, minutes) in summary.months.iter().enumerate() {
+        let name = NaiveDate::from_ymd_opt(year, i as u32 + 1, 1).map(|d| d.format("%b").to_string()).unwrap_or_default();
+        println!("{:<5}  {:>7}", name, minutes);
+    }
+    Ok(())
+}
+
+fn export_csv(path: &str) -> io::Result<()> {
+    let store = JsonHistory::read()?;
+    let (csv, skipped) = render_csv(&store);
+    for key in &skipped {
+        eprintln!("fokus: skipping unparsable date {:?}", key);
+    }
+    if path == "-" {
+        print!("{}", csv);
+    } else {
+        fs::write(path, csv)?;
+        println!("Exported history to {}", path);
+    }
+    Ok(())
+}
+
+/// Writes the same CSV as --export-csv to a timestamped file next to the
+/// history, returning its path.
+fn export_csv_file(store: &dyn HistoryStore) -> io::Result<PathBuf> {
+    let dir = history_path()
+        .as_deref()
+        .and_then(Path::parent)
+        .map(Path::to_path_buf)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Config directory not found"))?;
+    let path = dir.join(format!("history_{}.csv", Local::now().format("%Y%m%d_%H%M%S")));
+    fs::create_dir_all(&dir)?;
+    fs::write(&path, render_csv(store).0)?;
+    Ok(path)
+}
+
+fn parse_date_range(s: &str) -> Option<(NaiveDate, NaiveDate)> {
+    if let Some((from, to)) = s.split_once("..") {
+        let from = NaiveDate::parse_from_str(from, "%Y-%m-%d").ok()?;
+        let to = NaiveDate::parse_from_str(to, "%Y-%m-%d").ok()?;
+        return (from <= to).then_some((from, to));
+    }
+
+    if let Ok(d) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return Some((d, d));
+    }
+
+    if let Ok(from) = NaiveDate::parse_from_str(&format!("{}-01", s), "%Y-%m-%d") {
+        let next = from.checked_add_months(chrono::Months::new(1))?;
+        return Some((from, next.pred_opt()?));
+    }
+
+    let year = s.parse::<i32>().ok().filter(|_| s.len() == 4)?;
+    Some((NaiveDate::from_ymd_opt(year, 1, 1)?, NaiveDate::from_ymd_opt(year, 12, 31)?))
+}
+
+/// Pads `s` with spaces to `width` terminal columns, counting wide characters
+/// (CJK, most emoji) as two, unlike `format!`'s padding.
+fn pad_right(s: &str, width: usize) -> String {
+    format!("{}{}", s, " ".repeat(width.saturating_sub(s.width())))
+}
+
+fn pad_left(s: &str, width: usize) -> String {
+    format!("{}{}", " ".repeat(width.saturating_sub(s.width())), s)
+}
+
+/// A history key as configured by `date_display_format`. Keys that aren't
+/// dates are shown as they are.
+fn display_date(key: &str, format: &str) -> String {
+    match NaiveDate::parse_from_str(key, "%Y-%m-%d") {
+        Ok(d) => d.format(format).to_string(),
+        Err(_) => key.to_string(),
+    }
+}
+
+fn format_range(from: NaiveDate, to: NaiveDate) -> String {
+    let month_end = from.checked_add_months(chrono::Months::new(1)).and_then(|d| d.pred_opt());
+    if from.day() == 1 && from.month() == 1 && to == from.with_month(12).and_then(|d| d.with_day(31)).unwrap_or(from) {
+        from.format("%Y").to_string()
+    } else if from.day() == 1 && Some(to) == month_end {
+        from.format("%Y-%m").to_string()
+    } else if from == to {
+        from.format("%Y-%m-%d").to_string()
+    } else {
+        format!("{}..{}", from.format("%Y-%m-%d"), to.format("%Y-%m-%d"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(s: u64) -> Duration {
+        Duration::from_secs(s)
+    }
+
+    /// A store that keeps everything in memory and counts its flushes.
+    #[derive(Default)]
+    struct MemoryHistory {
+        days: HashMap<String, u64>,
+        tags: TagHistory,
+        flushes: usize,
+    }
+
+    impl MemoryHistory {
+        fn with(entries: &[(&str, u64)]) -> MemoryHistory {
+            MemoryHistory { days: days(entries), ..MemoryHistory::default() }
+        }
+    }
+
+    impl HistoryStore for MemoryHistory {
+        fn total_for(&self, day: &str) -> u64 {
+            self.days.get(day).copied().unwrap_or(0)
+        }
+
+        fn entries(&self) -> Vec<(String, u64)> {
+            self.days.iter().map(|(k, v)| (k.clone(), *v)).collect()
+        }
+
+        fn tags_on(&self, day: &str) -> Vec<(String, u64)> {
+            self.tags.get(day).into_iter().flatten().map(|(k, v)| (k.clone(), *v)).collect()
+        }
+
+        fn add(&mut self, day: &str, tag: Option<&str>, seconds: u64) -> u64 {
+            if let Some(tag) = tag {
+                *self.tags.entry(day.to_string()).or_default().entry(tag.to_string()).or_insert(0) += seconds;
+            }
+            let total = self.days.entry(day.to_string()).or_insert(0);
+            *total += seconds;
+            *total
+        }
+
+        fn subtract(&mut self, day: &str, tag: Option<&str>, seconds: u64) -> u64 {
+            let removed = seconds.min(self.total_for(day));
+            self.days.insert(day.to_string(), self.total_for(day) - removed);
+            self.days.retain(|_k, v| *v > 0);
+            if let Some(tag) = tag && let Some(day_tags) = self.tags.get_mut(day) {
+                day_tags.entry(tag.to_string()).and_modify(|v| *v = v.saturating_sub(seconds));
+                day_tags.retain(|_k, v| *v > 0);
+            }
+            self.tags.retain(|_k, t| !t.is_empty());
+            removed
+        }
+
+        fn delete(&mut self, day: &str) -> u64 {
+            self.tags.remove(day);
+            self.days.remove(day).unwrap_or(0)
+        }
+
+        fn move_day(&mut self, from: &str, to: &str) -> bool {
+            let merged = self.days.contains_key(to);
+            let secs = self.delete(from);
+            self.add(to, None, secs);
+            merged
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.flushes += 1;
+            Ok(())
+        }
+    }
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    fn at(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    #[test]
+    fn log_seconds_until_adds_to_the_day_and_tag_and_saves() {
+        let mut store = MemoryHistory::with(&[("2025-06-01", 600)]);
+        log_seconds_until(&mut store, at("2025-06-01 12:00:00"), Some("writing"), 300);
+        log_seconds_until(&mut store, at("2025-06-02 12:00:00"), None, 60);
+        assert_eq!(store.total_for("2025-06-01"), 900);
+        assert_eq!(store.tags_on("2025-06-01"), vec![("writing".to_string(), 300)]);
+        assert_eq!(store.total_for("2025-06-02"), 60);
+        assert_eq!(store.flushes, 2);
+
+        log_seconds_until(&mut store, at("2025-06-03 12:00:00"), None, 0);
+        assert_eq!(store.entries().len(), 2, "nothing is logged for zero seconds");
+        assert_eq!(store.flushes, 2);
+    }
+
+    #[test]
+    fn add_minutes_to_adds_to_an_existing_day() {
+        let mut store = MemoryHistory::with(&[("2025-06-01", 600)]);
+        assert_eq!(add_minutes_to(&mut store, "2025-06-01", 45).unwrap(), 600 + 45 * 60);
+        assert_eq!(add_minutes_to(&mut store, "2025-06-02", 5).unwrap(), 300);
+        assert_eq!(store.total_for("2025-06-01"), 3300);
+
+        let err = add_minutes_to(&mut store, "2025-06-01", 400_000_000_000_000_000).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(store.total_for("2025-06-01"), 3300);
+    }
+
+    #[test]
+    fn clear_day_removes_only_that_day() {
+        let mut store = MemoryHistory::with(&[("2025-06-01", 600), ("2025-06-02", 1200)]);
+        store.add("2025-06-02", Some("writing"), 60);
+        assert_eq!(clear_day(&mut store, "2025-06-02").unwrap(), 1260);
+        assert_eq!(store.entries(), vec![("2025-06-01".to_string(), 600)]);
+        assert!(store.tags_on("2025-06-02").is_empty());
+        assert_eq!(store.flushes, 1);
+        assert_eq!(clear_day(&mut store, "2025-06-02").unwrap(), 0);
+    }
+
+    #[test]
+    fn undo_session_takes_back_what_was_logged() {
+        let mut store = MemoryHistory::with(&[("2025-06-01", 600)]);
+        log_seconds_until(&mut store, at("2025-06-01 12:00:00"), Some("writing"), 300);
+        let session = LoggedSession { day: "2025-06-01".to_string(), tag: Some("writing".to_string()), seconds: 300, ended_at: None, completed: false };
+        assert_eq!(undo_session(&mut store, &session), 300);
+        assert_eq!(store.total_for("2025-06-01"), 600);
+        assert!(store.tags_on("2025-06-01").is_empty());
+        assert_eq!(store.flushes, 2);
+    }
+
+    #[test]
+    fn timer_tick_fires_exactly_once() {
+        let t0 = Instant::now();
+        let mut timer = TimerState::new(secs(60));
+        assert!(!timer.tick(t0 + secs(120)), "an idle timer never fires");
+        timer.start(t0);
+        assert!(!timer.tick(t0 + secs(59)));
+        assert!(timer.tick(t0 + secs(60)));
+        assert!(timer.done && !timer.running);
+        assert!(!timer.tick(t0 + secs(61)));
+        assert_eq!(timer.remaining(t0 + secs(61)), Duration::ZERO);
+        assert_eq!(timer.partial_seconds(t0 + secs(61)), 0, "a finished interval is logged by tick");
+    }
+
+    #[test]
+    fn timer_pause_and_stop() {
+        let t0 = Instant::now();
+        let mut timer = TimerState::new(secs(60));
+        timer.start(t0);
+        timer.toggle_pause(t0 + secs(20));
+        assert!(timer.is_paused() && !timer.is_idle());
+        assert_eq!(timer.remaining(t0 + secs(50)), secs(40), "paused time doesn't count");
+        timer.toggle_pause(t0 + secs(50));
+        assert!(!timer.tick(t0 + secs(89)));
+        assert!(timer.tick(t0 + secs(90)));
+
+        timer.start(t0);
+        timer.stop();
+        assert!(timer.is_idle());
+        assert_eq!(timer.partial_seconds(t0 + secs(30)), 0);
+    }
+
+    #[test]
+    fn timer_flush_counts_each_second_once() {
+        let t0 = Instant::now();
+        let mut timer = TimerState::new(secs(600));
+        timer.start(t0);
+        assert_eq!(timer.partial_seconds(t0 + secs(100)), 100);
+        assert_eq!(timer.flush(t0 + secs(100)), 100);
+        assert_eq!(timer.partial_seconds(t0 + secs(100)), 0);
+        assert_eq!(timer.partial_seconds(t0 + secs(150)), 50);
+        assert_eq!(timer.flush(t0 + secs(150)), 50);
+        timer.start(t0 + secs(200));
+        assert_eq!(timer.flushed, 0, "a new interval starts unflushed");
+    }
+
+    #[test]
+    fn stopwatch_toggle_reset_and_flush() {
+        let t0 = Instant::now();
+        let mut stopwatch = StopwatchState::new();
+        assert!(stopwatch.is_idle());
+        stopwatch.toggle(t0);
+        stopwatch.toggle(t0 + secs(30));
+        assert!(!stopwatch.running && !stopwatch.is_idle());
+        assert_eq!(stopwatch.elapsed(t0 + secs(100)), secs(30), "stopped time doesn't count");
+        stopwatch.toggle(t0 + secs(100));
+        assert_eq!(stopwatch.elapsed(t0 + secs(110)), secs(40));
+
+        assert_eq!(stopwatch.flush(t0 + secs(110)), 40);
+        assert_eq!(stopwatch.unlogged_seconds(t0 + secs(110)), 0);
+        assert_eq!(stopwatch.reset(t0 + secs(125)), 15, "reset returns only what wasn't flushed");
+        assert!(stopwatch.is_idle());
+        assert_eq!(stopwatch.flushed, 0);
+    }
+
+    fn days(entries: &[(&str, u64)]) -> HashMap<String, u64> {
+        entries.iter().map(|(k, v)| (k.to_string(), *v)).collect()
+    }
+
+    /// A directory of its own under the system temp dir for a test that
+    /// needs real files.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("fokus-test-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn write_atomic_round_trips_a_large_history() {
+        let dir = temp_dir("write-atomic");
+        let path = dir.join("history.json");
+        let start = date("2000-01-01");
+        let data: HashMap<String, u64> = start.iter_days().take(20_000)
+            .enumerate()
+            .map(|(i, d)| (d.format("%Y-%m-%d").to_string(), i as u64 * 61))
+            .collect();
+        let file = HistoryFile { version: HISTORY_VERSION, data: data.clone(), tags: TagHistory::new() };
+        let s = serde_json::to_string_pretty(&file).unwrap();
+        fs::write(&path, "old contents").unwrap();
+        write_atomic(&path, &s).unwrap();
+
+        let (read, tags, upgraded) = parse_history(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(read, data);
+        assert!(tags.is_empty() && !upgraded);
+        let names: Vec<_> = fs::read_dir(&dir).unwrap().map(|e| e.unwrap().file_name()).collect();
+        assert_eq!(names, ["history.json"], "no temporary file is left behind");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn parse_history_upgrades_bare_minutes() {
+        let (data, tags, upgraded) = parse_history(r#"{"2025-06-01": 25, "2025-06-02": 0}"#).unwrap();
+        assert_eq!(data, days(&[("2025-06-01", 1500), ("2025-06-02", 0)]));
+        assert!(tags.is_empty());
+        assert!(upgraded, "a bare map is the old format and gets rewritten");
+        assert!(check_history_version(r#"{"2025-06-01": 25}"#).is_ok());
+    }
+
+    #[test]
+    fn parse_history_reads_version_one() {
+        let s = r#"{"version": 1, "data": {"2025-06-01": 1500}, "tags": {"2025-06-01": {"writing": 900}}}"#;
+        assert!(check_history_version(s).is_ok());
+        let (data, tags, upgraded) = parse_history(s).unwrap();
+        assert_eq!(data, days(&[("2025-06-01", 1500)]));
+        assert_eq!(tags["2025-06-01"], days(&[("writing", 900)]));
+        assert!(!upgraded);
+
+        let (_data, tags, _upgraded) = parse_history(r#"{"version": 1, "data": {}}"#).unwrap();
+        assert!(tags.is_empty(), "tags are optional");
+        assert!(parse_history("not json").is_none());
+    }
+
+    #[test]
+    fn check_history_version_refuses_newer_files() {
+        let s = format!(r#"{{"version": {}, "data": {{}}}}"#, HISTORY_VERSION + 1);
+        let err = check_history_version(&s).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn apply_delta_keeps_changes_made_on_disk() {
+        // Loaded with two days; another instance then added to one and
+        // logged a new one, while this one added, subtracted and deleted.
+        let base = days(&[("2025-06-01", 600), ("2025-06-02", 300), ("2025-06-03", 120)]);
+        let mut disk = days(&[("2025-06-01", 900), ("2025-06-02", 300), ("2025-06-03", 120), ("2025-06-04", 60)]);
+        let mine = days(&[("2025-06-01", 660), ("2025-06-02", 200), ("2025-06-05", 30)]);
+        apply_delta(&mut disk, &base, &mine);
+        assert_eq!(
+            disk,
+            days(&[("2025-06-01", 960), ("2025-06-02", 200), ("2025-06-04", 60), ("2025-06-05", 30)]),
+        );
+    }
+
+    #[test]
+    fn apply_delta_never_goes_below_zero() {
+        let base = days(&[("2025-06-01", 600)]);
+        let mut disk = days(&[("2025-06-01", 100)]);
+        apply_delta(&mut disk, &base, &days(&[("2025-06-01", 300)]));
+        assert!(disk.is_empty(), "a day that reaches zero is removed");
+    }
+
+    #[test]
+    fn merge_history_merges_tags_too() {
+        let tags = |entries: &[(&str, &[(&str, u64)])]| -> TagHistory {
+            entries.iter().map(|(day, t)| (day.to_string(), days(t))).collect()
+        };
+        let base_days = days(&[("2025-06-01", 600)]);
+        let base_tags = tags(&[("2025-06-01", &[("writing", 600)])]);
+        let mut disk_days = days(&[("2025-06-01", 900)]);
+        let mut disk_tags = tags(&[("2025-06-01", &[("writing", 600), ("coding", 300)])]);
+        // This instance deleted the day.
+        let (mine_days, mine_tags) = (HashMap::new(), TagHistory::new());
+        merge_history((&mut disk_days, &mut disk_tags), (&base_days, &base_tags), (&mine_days, &mine_tags));
+        assert_eq!(disk_days, days(&[("2025-06-01", 300)]), "only what was there at load is removed");
+        assert_eq!(disk_tags, tags(&[("2025-06-01", &[("coding", 300)])]));
+
+        let mut disk_tags = tags(&[("2025-06-01", &[("writing", 600)])]);
+        let mut disk_days = base_days.clone();
+        merge_history((&mut disk_days, &mut disk_tags), (&base_days, &base_tags), (&mine_days, &mine_tags));
+        assert!(disk_days.is_empty());
+        assert!(disk_tags.is_empty(), "a day left without tags is dropped");
+    }
+
+    #[test]
+    fn saving_keeps_what_changed_on_disk_since_loading() {
+        let path = temp_dir("merge").join("history.json");
+        let write_behind = |entries: &[(&str, u64)]| {
+            let file = HistoryFile { version: HISTORY_VERSION, data: days(entries), tags: TagHistory::new() };
+            fs::write(&path, serde_json::to_string(&file).unwrap()).unwrap();
+        };
+        write_behind(&[("2025-06-01", 600), ("2025-06-02", 300), ("2025-06-03", 120)]);
+
+        // Loaded as read_history does, leaving the baseline behind.
+        let (mut map, mut tags) = read_disk_history(&path).unwrap();
+        let mut saved = Some((map.clone(), tags.clone()));
+
+        // This instance logs a session and deletes a day, while --add (or
+        // another instance) adds to the same day and logs a new one.
+        *map.get_mut("2025-06-01").unwrap() += 60;
+        map.remove("2025-06-02");
+        tags.entry("2025-06-01".to_string()).or_default().insert("writing".to_string(), 60);
+        write_behind(&[("2025-06-01", 900), ("2025-06-02", 300), ("2025-06-03", 120), ("2025-06-04", 1200)]);
+
+        save_history_to(&path, &mut map, &mut tags, &mut saved).unwrap();
+        let expected = days(&[("2025-06-01", 960), ("2025-06-03", 120), ("2025-06-04", 1200)]);
+        let (on_disk, disk_tags) = read_disk_history(&path).unwrap();
+        assert_eq!(on_disk, expected);
+        assert_eq!(disk_tags["2025-06-01"], days(&[("writing", 60)]));
+        assert_eq!(map, expected, "the store sees the merged history");
+        assert_eq!(saved.as_ref().map(|(m, _t)| m), Some(&expected), "the baseline is what was written");
+
+        // The next save only applies what changed since the last one.
+        write_behind(&[("2025-06-01", 960), ("2025-06-03", 180), ("2025-06-04", 1200)]);
+        map.remove("2025-06-04");
+        save_history_to(&path, &mut map, &mut tags, &mut saved).unwrap();
+        assert_eq!(read_disk_history(&path).unwrap().0, days(&[("2025-06-01", 960), ("2025-06-03", 180)]));
+
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn in_progress_seconds_leaves_out_what_midnight_logged() {
+        let t0 = Instant::now();
+        let midnight = t0 + secs(600);
+        let now = midnight + secs(120);
+        let idle = StopwatchState::new();
+
+        // A focus interval that started ten minutes before midnight.
+        let mut timer = TimerState::new(secs(25 * 60));
+        timer.start(t0);
+        assert_eq!(timer.flush(midnight), 600);
+        assert_eq!(in_progress_seconds(&idle, &timer, Phase::Work, now), 120);
+        assert_eq!(in_progress_seconds(&idle, &timer, Phase::ShortBreak, now), 0);
+
+        let mut stopwatch = StopwatchState::new();
+        stopwatch.toggle(t0);
+        assert_eq!(stopwatch.flush(midnight), 600);
+        assert_eq!(in_progress_seconds(&stopwatch, &TimerState::new(secs(60)), Phase::Work, now), 120);
+    }
+
+    #[test]
+    fn human_minutes_switches_to_hours_at_sixty() {
+        assert_eq!(human_minutes(0), "0 minutes");
+        assert_eq!(human_minutes(59), "59 minutes");
+        assert_eq!(human_minutes(60), "1h 0m");
+        assert_eq!(human_minutes(61), "1h 1m");
+        assert_eq!(human_minutes(125), "2h 5m");
+    }
+
+    #[test]
+    fn record_day_takes_the_most_and_the_latest_on_a_tie() {
+        assert_eq!(record_day(Vec::new()), None);
+        assert_eq!(record_day(vec![(date("2025-06-01"), 0)]), None, "empty days don't count");
+
+        let store = MemoryHistory::with(&[
+            ("2025-06-01", 3600),
+            ("2025-06-02", 7200),
+            ("2025-06-03", 7200),
+            ("2025-06-04", 60),
+            ("not a date", 99_999),
+        ]);
+        assert_eq!(record_day(store.dated()), Some((date("2025-06-03"), 7200)));
+    }
+
+    #[test]
+    fn round_session_in_each_mode() {
+        let (m3s29, m3s31, m3s01) = (3 * 60 + 29, 3 * 60 + 31, 3 * 60 + 1);
+        let round = |mode: &str| [m3s29, m3s31, m3s01].map(|s| round_session(s, mode));
+        assert_eq!(round("exact"), [m3s29, m3s31, m3s01]);
+        assert_eq!(round("floor"), [180, 180, 180]);
+        assert_eq!(round("round"), [180, 240, 180]);
+        assert_eq!(round("ceil"), [240, 240, 240]);
+    }
+
+    fn session_at(ended_at: &str, seconds: u64, completed: bool) -> SessionRecord {
+        SessionRecord { ended_at: ended_at.to_string(), seconds, tag: None, note: None, completed }
+    }
+
+    #[test]
+    fn summary_counts_todays_sessions() {
+        let today = Local::now();
+        let yesterday = today - chrono::Days::new(1);
+        let rfc = |t: DateTime<Local>| t.to_rfc3339_opts(chrono::SecondsFormat::Secs, false);
+        let sessions = [
+            session_at(&rfc(yesterday), 1500, true),
+            session_at(&rfc(today), 1500, true),
+            session_at(&rfc(today), 600, false),
+            session_at("not a time", 600, false),
+        ];
+        assert_eq!(sessions_on(&sessions, today.date_naive()), 2);
+
+        assert_eq!(summary_parts(75, 3, 120, 5), ["75m today", "3 sessions", "62% of goal", "5-day streak"]);
+        assert_eq!(summary_parts(20, 1, 0, 0), ["20m today", "1 session", "0-day streak"]);
+
+        let store = MemoryHistory::with(&[(&today.format("%Y-%m-%d").to_string(), 75 * 60)]);
+        assert_eq!(today_summary(&store, 3), "Focused 75m today across 3 sessions, 1-day streak");
+    }
+
+    #[test]
+    fn parse_color_reads_hex_and_names() {
+        assert_eq!(parse_color("#ff8000"), Some(Color::Rgb(255, 128, 0)));
+        assert_eq!(parse_color(" #00AAff "), Some(Color::Rgb(0, 170, 255)));
+        assert_eq!(parse_color("#12345"), None);
+        assert_eq!(parse_color("#1234567"), None);
+        assert_eq!(parse_color("#gg0000"), None);
+        assert_eq!(parse_color("#"), None);
+        assert_eq!(parse_color("red"), Some(Color::Red));
+        assert_eq!(parse_color("not a color"), None);
+    }
+
+    #[test]
+    fn render_csv_round_trips_a_known_history() {
+        let known = [("2025-06-02", 1500), ("2024-12-31", 3600), ("2025-06-01", 59), ("2025-01-10", 0)];
+        let mut store = MemoryHistory::with(&known);
+        store.add("notes", None, 600);
+        store.add("2025-13-01", None, 60);
+
+        let (csv, skipped) = render_csv(&store);
+        assert_eq!(skipped, vec!["2025-13-01".to_string(), "notes".to_string()]);
+
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some("date,minutes"));
+        let rows: Vec<(String, u64)> = lines
+            .map(|l| {
+                let (day, minutes) = l.split_once(',').unwrap();
+                (day.to_string(), minutes.parse().unwrap())
+            })
+            .collect();
+        let dates: Vec<&str> = rows.iter().map(|(d, _m)| d.as_str()).collect();
+        assert_eq!(dates, ["2024-12-31", "2025-01-10", "2025-06-01", "2025-06-02"]);
+        for (day, minutes) in &rows {
+            let (_k, secs) = known.iter().find(|(k, _secs)| k == day).unwrap();
+            assert_eq!(*minutes, secs / 60);
+        }
+    }
+
+    #[test]
+    fn goal_progress_is_met_at_the_goal() {
+        assert_eq!(goal_progress(119, 120), Some(("119 / 120 minutes (99%)".to_string(), false)));
+        assert_eq!(goal_progress(120, 120), Some(("120 / 120 minutes (100%)".to_string(), true)));
+        assert_eq!(goal_progress(150, 120), Some(("150 / 120 minutes (125%)".to_string(), true)));
+        assert_eq!(goal_progress(0, 120), Some(("0 / 120 minutes (0%)".to_string(), false)));
+        assert_eq!(goal_progress(45, 0), None);
+    }
+
+    #[test]
+    fn format_duration_switches_to_hours_at_an_hour() {
+        assert_eq!(format_duration(secs(59)), "00:59.00");
+        assert_eq!(format_duration(Duration::from_millis(59_990)), "00:59.99");
+        assert_eq!(format_duration(secs(59 * 60 + 59)), "59:59.00");
+        assert_eq!(format_duration(secs(60 * 60)), "1:00:00");
+        assert_eq!(format_duration(secs(61 * 60)), "1:01:00");
+        assert_eq!(format_duration(secs(999 * 60)), "16:39:00");
+    }
+
+    #[test]
+    fn in_quiet_hours_handles_windows_across_midnight() {
+        let t = |h: u32, m: u32| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+
+        assert!(in_quiet_hours(t(13, 0), t(12, 0), t(14, 0)));
+        assert!(in_quiet_hours(t(12, 0), t(12, 0), t(14, 0)), "the start is quiet");
+        assert!(!in_quiet_hours(t(14, 0), t(12, 0), t(14, 0)), "the end is not");
+        assert!(!in_quiet_hours(t(11, 59), t(12, 0), t(14, 0)));
+
+        assert!(in_quiet_hours(t(23, 30), t(22, 0), t(7, 0)));
+        assert!(in_quiet_hours(t(0, 0), t(22, 0), t(7, 0)));
+        assert!(in_quiet_hours(t(6, 59), t(22, 0), t(7, 0)));
+        assert!(!in_quiet_hours(t(7, 0), t(22, 0), t(7, 0)));
+        assert!(!in_quiet_hours(t(12, 0), t(22, 0), t(7, 0)));
+
+        for now in [t(0, 0), t(9, 0), t(9, 1), t(23, 59)] {
+            assert!(!in_quiet_hours(now, t(9, 0), t(9, 0)), "an empty window is never quiet");
+        }
+    }
+
+    #[test]
+    fn padding_lines_up_wide_labels() {
+        let labels = ["2025-06-01", "日本語", "café", "読書 notes"];
+        let width = labels.iter().map(|l| l.width()).max().unwrap();
+        let rows: Vec<String> = labels.iter()
+            .map(|l| format!("{} | {}", pad_right(l, width), pad_left("25", 6)))
+            .collect();
+        for row in &rows {
+            assert_eq!(row.width(), rows[0].width(), "{:?}", row);
+            let bar = row.find('|').unwrap();
+            assert_eq!(row[..bar].width(), width + 1, "{:?}", row);
+        }
+        assert_eq!(pad_right("日本", 6), "日本  ");
+        assert_eq!(pad_left("日本", 6), "  日本");
+        assert_eq!(pad_right("日本語", 4), "日本語", "never truncated");
+    }
+
+    #[test]
+    fn week_of_follows_the_week_start() {
+        let sunday = date("2025-06-01");
+        let monday = date("2025-06-02");
+        let cfg = |week_start: &str| Config { week_start: week_start.to_string(), ..Config::default() };
+
+        let first = cfg("monday").first_weekday();
+        assert_eq!(week_of(sunday, first), date("2025-05-26"), "Sunday ends a Monday week");
+        assert_eq!(week_of(monday, first), date("2025-06-02"));
+
+        let first = cfg("sunday").first_weekday();
+        assert_eq!(week_of(sunday, first), date("2025-06-01"));
+        assert_eq!(week_of(monday, first), date("2025-06-01"), "Monday is in the week its Sunday started");
+
+        let store = MemoryHistory::with(&[("2025-05-31", 60), ("2025-06-01", 600), ("2025-06-02", 1200)]);
+        assert_eq!(week_seconds(&store, monday, Weekday::Mon), 1200);
+        assert_eq!(week_seconds(&store, monday, Weekday::Sun), 1800);
+    }
+
+    #[test]
+    fn merge_days_sums_overlapping_histories() {
+        let mut store = MemoryHistory::with(&[("2025-06-01", 600), ("2025-06-02", 1200)]);
+        store.add("2025-06-02", Some("writing"), 300);
+        let old = days(&[("2025-05-31", 60), ("2025-06-02", 900), ("someday", 120)]);
+        let mut old_tags = TagHistory::new();
+        old_tags.insert("2025-06-02".to_string(), days(&[("writing", 600), ("reading", 300)]));
+        old_tags.insert("2025-05-31".to_string(), days(&[("reading", 600)]));
+
+        let (imported, added, skipped) = merge_days(&mut store, &old, &old_tags);
+        assert_eq!((imported, added), (2, 960));
+        assert_eq!(skipped, vec!["someday".to_string()]);
+
+        assert_eq!(store.total_for("2025-05-31"), 60);
+        assert_eq!(store.total_for("2025-06-01"), 600);
+        assert_eq!(store.total_for("2025-06-02"), 1200 + 300 + 900);
+        assert_eq!(store.total_for("someday"), 0);
+        let mut tags = store.tags_on("2025-06-02");
+        tags.sort();
+        assert_eq!(tags, vec![("reading".to_string(), 300), ("writing".to_string(), 900)]);
+        assert_eq!(store.tags_on("2025-05-31"), vec![("reading".to_string(), 60)], "a tag never outgrows its day");
+    }
+
+    #[test]
+    fn finishing_a_timer_counts_a_pomodoro() {
+        let local = |s: &str| at(s).and_local_timezone(Local).earliest().unwrap();
+        let t0 = Instant::now();
+        let mut store = MemoryHistory::default();
+        let mut undo = Vec::new();
+        let mut pomodoros = PomodoroCount { day: date("2025-06-01"), count: 0 };
+        let mut timer = TimerState::new(secs(25 * 60));
+
+        // Stopping early is not a pomodoro, and nothing here counts it.
+        timer.start(t0);
+        timer.stop();
+        assert_eq!(pomodoros.on(date("2025-06-01")), 0);
+
+        timer.start(t0);
+        assert!(timer.tick(t0 + secs(25 * 60)));
+        let (session, reached) = finish_pomodoro(&mut store, &mut undo, &mut pomodoros, &timer, Some("writing"), 2, local("2025-06-01 22:00:00"));
+        assert!(session.completed && session.seconds == 25 * 60);
+        assert_eq!(session.ended_on(), Some(date("2025-06-01")));
+        assert!(!reached);
+        assert_eq!(pomodoros.on(date("2025-06-01")), 1);
+        assert_eq!(store.total_for("2025-06-01"), 25 * 60);
+        assert_eq!(undo.len(), 1);
+
+        let (_session, reached) = finish_pomodoro(&mut store, &mut undo, &mut pomodoros, &timer, Some("writing"), 2, local("2025-06-01 22:30:00"));
+        assert!(reached, "the second one reaches the goal");
+        assert_eq!(pomodoros.on(date("2025-06-01")), 2);
+
+        // The next one runs past midnight: the count starts again for the new
+        // day, reaching the goal is news again, and its time is split.
+        let (session, reached) = finish_pomodoro(&mut store, &mut undo, &mut pomodoros, &timer, None, 1, local("2025-06-02 00:10:00"));
+        assert!(reached);
+        assert_eq!(pomodoros.on(date("2025-06-02")), 1);
+        assert_eq!(pomodoros.on(date("2025-06-01")), 0, "only today's count is kept");
+        assert_eq!(store.total_for("2025-06-01"), 50 * 60 + 15 * 60);
+        assert_eq!(store.total_for("2025-06-02"), 10 * 60);
+        assert_eq!(undo.last().map(|u| u.day.as_str()), Some("2025-06-02"));
+
+        let line = serde_json::to_string(&session).unwrap();
+        let read: SessionRecord = serde_json::from_str(&line).unwrap();
+        assert_eq!(pomodoros_on(&[read], date("2025-06-02")), 1, "the count survives a restart: {}", line);
+
+        // Undoing it takes it back off today's count, but not another day's.
+        pomodoros.remove(date("2025-06-01"));
+        assert_eq!(pomodoros.on(date("2025-06-02")), 1);
+        pomodoros.remove(date("2025-06-02"));
+        assert_eq!(pomodoros.on(date("2025-06-02")), 0);
+    }
+
+    #[test]
+    fn top_level_quiet_hours_fill_an_empty_table() {
+        let t = |h: u32| NaiveTime::from_hms_opt(h, 0, 0).unwrap();
+
+        let mut cfg: Config = toml::from_str("quiet_hours_start = \"22:00\"\nquiet_hours_end = \"07:00\"\n").unwrap();
+        cfg.fold_quiet_hours();
+        assert!(cfg.quiet_hours.is_quiet(t(23)) && !cfg.quiet_hours.is_quiet(t(12)));
+        assert!(cfg.to_toml().contains("start = \"22:00\""), "a rewrite keeps the window");
+
+        let mut cfg: Config = toml::from_str(
+            "quiet_hours_start = \"22:00\"\nquiet_hours_end = \"07:00\"\n[quiet_hours]\nstart = \"12:00\"\nend = \"13:00\"\n",
+        ).unwrap();
+        cfg.fold_quiet_hours();
+        assert!(cfg.quiet_hours.is_quiet(t(12)) && !cfg.quiet_hours.is_quiet(t(23)), "the table wins");
+    }
+
+    #[test]
+    fn parse_last_caps_the_chart_length() {
+        assert_eq!(parse_last(Some("14")), Some(14));
+        assert_eq!(parse_last(Some("3660")), Some(SVG_MAX_DAYS));
+        assert_eq!(parse_last(Some("3661")), None);
+        assert_eq!(parse_last(Some("100000000")), None);
+        assert_eq!(parse_last(Some("0")), None);
+        assert_eq!(parse_last(Some("-5")), None);
+        assert_eq!(parse_last(None), None);
+    }
+
+    #[test]
+    fn filtered_stats_only_count_the_range() {
+        let store = MemoryHistory::with(&[("2025-05-31", 6000), ("2025-06-01", 1200), ("2025-06-02", 0), ("2025-06-03", 2400), ("notes", 60)]);
+        let june = Some((date("2025-06-01"), date("2025-06-30")));
+
+        let shown = days_in_range(store.dated(), june);
+        assert_eq!(shown, vec![(date("2025-06-01"), 1200), (date("2025-06-02"), 0), (date("2025-06-03"), 2400)]);
+        assert_eq!(all_time_stats(&shown), (60, 2, 30));
+        assert_eq!(record_day(shown), Some((date("2025-06-03"), 2400)));
+
+        assert_eq!(days_in_range(store.dated(), None).len(), 4);
+        assert_eq!(record_day(days_in_range(store.dated(), None)), Some((date("2025-05-31"), 6000)));
+    }
+
+    #[test]
+    fn undo_forgets_the_sessions_line_too() {
+        let mut undo = Vec::new();
+        let pomodoro = SessionRecord::pomodoro(Some("writing"), 1500, Local::now());
+        push_undo(&mut undo, &pomodoro, 1500);
+        let stopped = SessionRecord::new(None, 300, None);
+        push_undo(&mut undo, &stopped, 0);
+        assert_eq!(undo.len(), 1, "nothing was logged for the second one");
+
+        let older = SessionRecord { ended_at: "2025-06-01T09:00:00+00:00".to_string(), ..SessionRecord::new(Some("writing"), 1500, None) };
+        let line = |r: &SessionRecord| serde_json::to_string(r).unwrap();
+        let contents = format!("{}\nnot json\n{}\n{}\n", line(&older), line(&pomodoro), line(&stopped));
+
+        let session = undo.pop().unwrap();
+        assert!(session.completed, "undoing it takes one off the pomodoro count");
+        assert_eq!(
+            forget_session(&contents, &session).unwrap(),
+            format!("{}\nnot json\n{}\n", line(&older), line(&stopped)),
+        );
+        assert_eq!(forget_session(&format!("{}\n", line(&older)), &session), None);
+
+        let mut store = MemoryHistory::with(&[]);
+        store.add(&session.day, Some("writing"), 1500);
+        assert_eq!(undo_session(&mut store, &session), 1500);
+        assert_eq!(store.total_for(&session.day), 0);
+    }
+
+    #[test]
+    fn no_save_is_refused_for_commands_that_write() {
+        for cmd in ["--add", "--reset-today", "--clear-history", "--import", "--restore-backup", "--edit-config"] {
+            assert!(writes_to_disk(cmd), "{}", cmd);
+        }
+        for cmd in ["--today", "--export-csv", "--export-svg", "--year-summary", "--list-backups"] {
+            assert!(!writes_to_disk(cmd), "{}", cmd);
+        }
+    }
+
+    #[test]
+    fn only_key_presses_move_the_idle_deadline() {
+        use crossterm::event::{KeyEvent, KeyModifiers};
+
+        let t0 = Instant::now();
+        let later = t0 + secs(9 * 60);
+        for event in [Event::Resize(80, 24), Event::FocusGained, Event::FocusLost, Event::Paste("x".to_string())] {
+            assert_eq!(last_activity(t0, &event, later), t0, "{:?}", event);
+        }
+        let key = Event::Key(KeyEvent::new(KeyCode::Char('j'), KeyModifiers::NONE));
+        assert_eq!(last_activity(t0, &key, later), later);
+
+        assert!(!idle_for(t0, t0 + secs(10 * 60 - 1), 10));
+        assert!(idle_for(t0, t0 + secs(10 * 60), 10));
+        assert!(!idle_for(t0, t0 + secs(24 * 3600), 0), "0 is disabled");
+    }
+
+    #[test]
+    fn split_at_midnight_divides_time_between_days() {
+        let split = |start: &str, end: &str| split_at_midnight(at(start), at(end));
+
+        assert_eq!(split("2025-06-01 22:00:00", "2025-06-01 23:00:00"), vec![(date("2025-06-01"), 3600)]);
+        assert_eq!(split("2025-06-01 23:50:00", "2025-06-02 00:10:00"), vec![(date("2025-06-01"), 600), (date("2025-06-02"), 600)]);
+        assert_eq!(split("2025-06-01 23:00:00", "2025-06-02 00:00:00"), vec![(date("2025-06-01"), 3600)], "ending at midnight");
+        assert_eq!(split("2025-06-02 00:00:00", "2025-06-02 00:00:01"), vec![(date("2025-06-02"), 1)], "starting at midnight");
+        assert_eq!(split("2025-06-01 23:59:59", "2025-06-02 00:00:01"), vec![(date("2025-06-01"), 1), (date("2025-06-02"), 1)]);
+        assert_eq!(
+            split("2025-06-01 23:00:00", "2025-06-03 01:00:00"),
+            vec![(date("2025-06-01"), 3600), (date("2025-06-02"), 86400), (date("2025-06-03"), 3600)],
+        );
+        assert!(split("2025-06-01 12:00:00", "2025-06-01 12:00:00").is_empty());
+        assert!(split("2025-06-01 12:00:00", "2025-06-01 11:00:00").is_empty());
+    }
+
+    #[test]
+    fn a_timer_finishing_after_midnight_is_split() {
+        let mut store = MemoryHistory::default();
+        let end = at("2025-06-02 00:10:00") + chrono::Duration::milliseconds(400);
+        log_seconds_until(&mut store, end, Some("writing"), 25 * 60);
+        assert_eq!(store.total_for("2025-06-01"), 15 * 60);
+        assert_eq!(store.total_for("2025-06-02"), 10 * 60);
+        assert_eq!(store.tags_on("2025-06-01"), vec![("writing".to_string(), 15 * 60)]);
+        assert_eq!(store.flushes, 1);
+    }
+
+    #[test]
+    fn today_and_year_summary_read_through_the_store() {
+        let store = MemoryHistory::with(&[("2025-06-01", 1530), ("2025-06-03", 3600), ("2024-12-31", 6000), ("notes", 60)]);
+        assert_eq!(render_today(&store, date("2025-06-01"), false), "25");
+        assert_eq!(render_today(&store, date("2025-06-02"), true), r#"{"date":"2025-06-02","minutes":0}"#);
+
+        let summary = year_summary(&store, 2025);
+        assert_eq!((summary.minutes, summary.active_days, summary.average), (85, 2, 42));
+        assert_eq!(summary.best_day, Some((date("2025-06-03"), 60)));
+        assert_eq!(summary.months[5], 85);
+        assert_eq!(summary.months.iter().sum::<u64>(), 85, "other years and stray keys are left out");
+    }
+
+    #[test]
+    fn quit_seconds_logs_what_is_not_logged_yet() {
+        let t0 = Instant::now();
+        let now = t0 + secs(100);
+        let exact = |_logged: u64, secs: u64| secs;
+        let ceil = |logged: u64, secs: u64| round_session(logged + secs, "ceil").saturating_sub(logged);
+
+        let mut stopwatch = StopwatchState::new();
+        stopwatch.toggle(t0);
+        stopwatch.flushed = 30;
+        let mut timer = TimerState::new(secs(25 * 60));
+        timer.start(t0);
+        timer.flushed = 40;
+        assert_eq!(quit_seconds(&mut stopwatch, &timer, Phase::Work, now, exact), (70, 60));
+        assert!(stopwatch.is_idle());
+
+        // Rounding applies to the whole session, autosaved part included.
+        let mut stopwatch = StopwatchState::new();
+        stopwatch.toggle(t0);
+        stopwatch.flushed = 30;
+        assert_eq!(quit_seconds(&mut stopwatch, &timer, Phase::Work, now, ceil), (90, 80));
+
+        let mut stopwatch = StopwatchState::new();
+        assert_eq!(quit_seconds(&mut stopwatch, &timer, Phase::ShortBreak, now, exact), (0, 0));
+    }
 }